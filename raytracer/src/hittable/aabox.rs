@@ -7,7 +7,10 @@ pub struct AABox {
 }
 
 impl AABox {
-    pub fn new<M: Material + Clone + 'static>(p0: Point3, p1: Point3, ptr: M) -> AABox {
+    pub fn new<M: Material + Clone + 'static>(p0: Point3, p1: Point3, mut ptr: M) -> AABox {
+        // the six faces share one emitter
+        let d = p1 - p0;
+        ptr.resolve_area(2.0 * (d.x * d.y + d.x * d.z + d.y * d.z));
         let mut sides = HittableList::new();
         sides.add(Arc::new(XYRect::new(
             p0.x,
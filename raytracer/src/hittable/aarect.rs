@@ -12,7 +12,8 @@ pub struct XYRect<M: Material> {
 }
 
 impl<M: Material> XYRect<M> {
-    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, mat: M) -> Self {
+    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, mut mat: M) -> Self {
+        mat.resolve_area((x1 - x0) * (y1 - y0));
         XYRect {
             mp: mat,
             x0,
@@ -22,6 +23,25 @@ impl<M: Material> XYRect<M> {
             k,
         }
     }
+    fn corner_edges(&self) -> (Point3, Vec3, Vec3) {
+        (
+            Point3 {
+                x: self.x0,
+                y: self.y0,
+                z: self.k,
+            },
+            Vec3 {
+                x: self.x1 - self.x0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: self.y1 - self.y0,
+                z: 0.0,
+            },
+        )
+    }
 }
 
 impl<M: Material> Hittable for XYRect<M> {
@@ -60,6 +80,34 @@ impl<M: Material> Hittable for XYRect<M> {
         rec.v = (y - self.y0) / (self.y1 - self.y0);
         Some(rec)
     }
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, v), 0.001, INFINITY) {
+            None => 0.0,
+            Some(rec) => {
+                let (corner, ex, ey) = self.corner_edges();
+                rect_pdf_value(origin, corner, ex, ey, v, rec.t)
+            }
+        }
+    }
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let (corner, ex, ey) = self.corner_edges();
+        rect_random(origin, corner, ex, ey)
+    }
+    fn power(&self) -> f64 {
+        self.mp
+            .emitted_power((self.x1 - self.x0) * (self.y1 - self.y0))
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        let (corner, ex, ey) = self.corner_edges();
+        flat_emission_weight(&self.mp, corner + (ex + ey) * 0.5, unit_vector(ex ^ ey), o)
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        Some(Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        })
+    }
 }
 
 pub struct XZRect<M: Material> {
@@ -72,7 +120,8 @@ pub struct XZRect<M: Material> {
 }
 
 impl<M: Material> XZRect<M> {
-    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, mat: M) -> XZRect<M> {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, mut mat: M) -> XZRect<M> {
+        mat.resolve_area((x1 - x0) * (z1 - z0));
         XZRect {
             mp: mat,
             x0,
@@ -82,6 +131,25 @@ impl<M: Material> XZRect<M> {
             k,
         }
     }
+    fn corner_edges(&self) -> (Point3, Vec3, Vec3) {
+        (
+            Point3 {
+                x: self.x0,
+                y: self.k,
+                z: self.z0,
+            },
+            Vec3 {
+                x: self.x1 - self.x0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: self.z1 - self.z0,
+            },
+        )
+    }
 }
 
 impl<M: Material> Hittable for XZRect<M> {
@@ -124,20 +192,29 @@ impl<M: Material> Hittable for XZRect<M> {
         match self.hit(&Ray::new(origin, v), 0.001, INFINITY) {
             None => 0.0,
             Some(rec) => {
-                let area = (self.x1 - self.x0) * (self.z1 - self.z0);
-                let distance_squared = rec.t * rec.t * v.length_squared();
-                let cosine = (dot(v, rec.normal) / v.length()).abs();
-                distance_squared / (cosine * area)
+                let (corner, ex, ey) = self.corner_edges();
+                rect_pdf_value(origin, corner, ex, ey, v, rec.t)
             }
         }
     }
     fn random(&self, origin: Vec3) -> Vec3 {
-        let random_point = Point3 {
-            x: random_double(self.x0, self.x1),
-            y: self.k,
-            z: random_double(self.z0, self.z1),
-        };
-        random_point - origin
+        let (corner, ex, ey) = self.corner_edges();
+        rect_random(origin, corner, ex, ey)
+    }
+    fn power(&self) -> f64 {
+        self.mp
+            .emitted_power((self.x1 - self.x0) * (self.z1 - self.z0))
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        let (corner, ex, ey) = self.corner_edges();
+        flat_emission_weight(&self.mp, corner + (ex + ey) * 0.5, unit_vector(ex ^ ey), o)
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        Some(Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        })
     }
 }
 
@@ -151,7 +228,8 @@ pub struct YZRect<M: Material> {
 }
 
 impl<M: Material> YZRect<M> {
-    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, mat: M) -> YZRect<M> {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, mut mat: M) -> YZRect<M> {
+        mat.resolve_area((y1 - y0) * (z1 - z0));
         YZRect {
             mp: mat,
             y0,
@@ -161,6 +239,25 @@ impl<M: Material> YZRect<M> {
             k,
         }
     }
+    fn corner_edges(&self) -> (Point3, Vec3, Vec3) {
+        (
+            Point3 {
+                x: self.k,
+                y: self.y0,
+                z: self.z0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: self.y1 - self.y0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: self.z1 - self.z0,
+            },
+        )
+    }
 }
 
 impl<M: Material> Hittable for YZRect<M> {
@@ -199,4 +296,32 @@ impl<M: Material> Hittable for YZRect<M> {
         rec.v = (z - self.z0) / (self.z1 - self.z0);
         Some(rec)
     }
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, v), 0.001, INFINITY) {
+            None => 0.0,
+            Some(rec) => {
+                let (corner, ex, ey) = self.corner_edges();
+                rect_pdf_value(origin, corner, ex, ey, v, rec.t)
+            }
+        }
+    }
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let (corner, ex, ey) = self.corner_edges();
+        rect_random(origin, corner, ex, ey)
+    }
+    fn power(&self) -> f64 {
+        self.mp
+            .emitted_power((self.y1 - self.y0) * (self.z1 - self.z0))
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        let (corner, ex, ey) = self.corner_edges();
+        flat_emission_weight(&self.mp, corner + (ex + ey) * 0.5, unit_vector(ex ^ ey), o)
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        Some(Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        })
+    }
 }
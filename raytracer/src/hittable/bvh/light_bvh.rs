@@ -0,0 +1,377 @@
+use crate::*;
+
+// Bounds of a group of emitters: where they are, how much they emit and
+// which way they face (a cone of unsigned normals with half-angle theta_o).
+#[derive(Clone, Copy)]
+pub struct LightBounds {
+    pub bbox: AABB,
+    pub power: f64,
+    pub axis: Vec3,
+    pub theta_o: f64,
+}
+
+impl LightBounds {
+    pub fn new(object: &dyn Hittable, power: f64) -> Option<LightBounds> {
+        let bbox = object.bounding_box(0.0, 1.0)?;
+        let (axis, theta_o) = match object.emission_normal() {
+            Some(n) => (unit_vector(n), 0.0),
+            None => (
+                Vec3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+                PI,
+            ),
+        };
+        Some(LightBounds {
+            bbox,
+            power,
+            axis,
+            theta_o,
+        })
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (self.bbox.minimum + self.bbox.maximum) * 0.5
+    }
+
+    // how much light this group is expected to send towards o
+    pub fn importance(&self, o: Point3) -> f64 {
+        if self.power <= 0.0 {
+            return 0.0;
+        }
+        let d = o - self.centroid();
+        let radius_squared = (self.bbox.maximum - self.bbox.minimum).length_squared() / 4.0;
+        let dist_squared = d.length_squared().max(radius_squared).max(1e-8);
+
+        let orientation = if self.theta_o >= PI || d.length_squared() <= radius_squared {
+            1.0
+        } else {
+            let dist = d.length();
+            let theta = clamp(dot(self.axis, d).abs() / dist, 0.0, 1.0).acos();
+            let theta_u = (radius_squared.sqrt() / dist).min(1.0).asin();
+            let theta_p = (theta - self.theta_o - theta_u).max(0.0);
+            if theta_p >= PI / 2.0 {
+                0.0
+            } else {
+                theta_p.cos()
+            }
+        };
+        self.power * orientation / dist_squared
+    }
+}
+
+fn rotate_towards(a: Vec3, b: Vec3, angle: f64) -> Vec3 {
+    // rotate a by angle in the plane spanned by a and b
+    let w = a ^ b;
+    if w.near_zero() {
+        return a;
+    }
+    let ortho = unit_vector(w ^ a);
+    unit_vector(a * angle.cos() + ortho * angle.sin())
+}
+
+pub fn union_light_bounds(a: &LightBounds, b: &LightBounds) -> LightBounds {
+    let bbox = surrounding_box(&a.bbox, &b.bbox);
+    let power = a.power + b.power;
+    let (axis, theta_o) = if a.theta_o >= PI || b.theta_o >= PI {
+        (a.axis, PI)
+    } else {
+        // normals are unsigned, so line the axes up first
+        let b_axis = if dot(a.axis, b.axis) < 0.0 {
+            -b.axis
+        } else {
+            b.axis
+        };
+        let theta_d = clamp(dot(a.axis, b_axis), -1.0, 1.0).acos();
+        if (theta_d + b.theta_o).min(PI) <= a.theta_o {
+            (a.axis, a.theta_o)
+        } else if (theta_d + a.theta_o).min(PI) <= b.theta_o {
+            (b_axis, b.theta_o)
+        } else {
+            let theta_o = (a.theta_o + theta_d + b.theta_o) / 2.0;
+            if theta_o >= PI {
+                (a.axis, PI)
+            } else {
+                (rotate_towards(a.axis, b_axis, theta_o - a.theta_o), theta_o)
+            }
+        }
+    };
+    LightBounds {
+        bbox,
+        power,
+        axis,
+        theta_o,
+    }
+}
+
+struct LightBvhNode {
+    bounds: LightBounds,
+    light: usize,
+    children: Option<Box<(LightBvhNode, LightBvhNode)>>,
+}
+
+impl LightBvhNode {
+    fn build(leaves: &mut [(usize, LightBounds)]) -> LightBvhNode {
+        if leaves.len() == 1 {
+            return LightBvhNode {
+                bounds: leaves[0].1,
+                light: leaves[0].0,
+                children: None,
+            };
+        }
+        let mut centroid_box = AABB::new(leaves[0].1.centroid(), leaves[0].1.centroid());
+        for (_, bounds) in leaves.iter() {
+            let c = bounds.centroid();
+            centroid_box = surrounding_box(&centroid_box, &AABB::new(c, c));
+        }
+        let extent = centroid_box.maximum - centroid_box.minimum;
+        let axis_value: fn(Point3) -> f64 = if extent.x >= extent.y && extent.x >= extent.z {
+            |p| p.x
+        } else if extent.y >= extent.z {
+            |p| p.y
+        } else {
+            |p| p.z
+        };
+        leaves.sort_by(|a, b| {
+            axis_value(a.1.centroid())
+                .partial_cmp(&axis_value(b.1.centroid()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mid = leaves.len() / 2;
+        let (left_leaves, right_leaves) = leaves.split_at_mut(mid);
+        let left = LightBvhNode::build(left_leaves);
+        let right = LightBvhNode::build(right_leaves);
+        LightBvhNode {
+            bounds: union_light_bounds(&left.bounds, &right.bounds),
+            light: 0,
+            children: Some(Box::new((left, right))),
+        }
+    }
+
+    fn child_probabilities(left: &LightBvhNode, right: &LightBvhNode, o: Point3) -> (f64, f64) {
+        let il = left.bounds.importance(o);
+        let ir = right.bounds.importance(o);
+        if il + ir <= 0.0 {
+            (0.5, 0.5)
+        } else {
+            (il / (il + ir), ir / (il + ir))
+        }
+    }
+
+    fn sample(&self, o: Point3) -> usize {
+        match &self.children {
+            None => self.light,
+            Some(children) => {
+                let (left, right) = (&children.0, &children.1);
+                let (pl, _) = Self::child_probabilities(left, right, o);
+                if random_double_default() < pl {
+                    left.sample(o)
+                } else {
+                    right.sample(o)
+                }
+            }
+        }
+    }
+
+    fn pdf_value(&self, lights: &[Arc<dyn Hittable>], o: Point3, v: Vec3, prob: f64) -> f64 {
+        match &self.children {
+            None => prob * lights[self.light].pdf_value(o, v),
+            Some(children) => {
+                let (left, right) = (&children.0, &children.1);
+                let (pl, pr) = Self::child_probabilities(left, right, o);
+                let r = Ray::new(o, v);
+                let mut sum = 0.0;
+                // a light can only be hit along v if its bounding box is
+                if pl > 0.0 && left.bounds.bbox.hit(&r, 0.001, INFINITY).is_some() {
+                    sum += left.pdf_value(lights, o, v, prob * pl);
+                }
+                if pr > 0.0 && right.bounds.bbox.hit(&r, 0.001, INFINITY).is_some() {
+                    sum += right.pdf_value(lights, o, v, prob * pr);
+                }
+                sum
+            }
+        }
+    }
+}
+
+// Light selection that takes distance and orientation into account.
+// Lights without a bounding box (e.g. distant lights) are kept aside and
+// chosen by power against the whole tree.
+pub struct LightBvh {
+    lights: Vec<Arc<dyn Hittable>>,
+    root: Option<LightBvhNode>,
+    infinite: Vec<usize>,
+    infinite_distribution: Distribution1D,
+    infinite_prob: f64,
+    total_power: f64,
+}
+
+impl LightBvh {
+    pub fn new(list: LightList) -> Self {
+        let lights = list.objects;
+        let total: f64 = list.powers.iter().sum();
+        let powers: Vec<f64> = if total > 0.0 {
+            list.powers
+        } else {
+            vec![1.0; lights.len()]
+        };
+
+        let mut leaves = Vec::new();
+        let mut infinite = Vec::new();
+        let mut infinite_powers = Vec::new();
+        let mut bounded_power = 0.0;
+        for (i, light) in lights.iter().enumerate() {
+            match LightBounds::new(light.as_ref(), powers[i]) {
+                Some(bounds) => {
+                    bounded_power += powers[i];
+                    leaves.push((i, bounds));
+                }
+                None => {
+                    infinite.push(i);
+                    infinite_powers.push(powers[i]);
+                }
+            }
+        }
+        let infinite_power: f64 = infinite_powers.iter().sum();
+        let infinite_prob = if leaves.is_empty() {
+            1.0
+        } else if infinite.is_empty() {
+            0.0
+        } else if infinite_power + bounded_power > 0.0 {
+            infinite_power / (infinite_power + bounded_power)
+        } else {
+            0.5
+        };
+        let root = if leaves.is_empty() {
+            None
+        } else {
+            Some(LightBvhNode::build(&mut leaves))
+        };
+        LightBvh {
+            lights,
+            root,
+            infinite,
+            infinite_distribution: Distribution1D::new(&infinite_powers),
+            infinite_prob,
+            total_power: bounded_power + infinite_power,
+        }
+    }
+}
+
+impl Hittable for LightBvh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest_so_far = t_max;
+        let mut rec: Option<HitRecord> = None;
+
+        for object in &self.lights {
+            if let Some(temp_rec) = object.hit(r, t_min, closest_so_far) {
+                closest_so_far = temp_rec.t;
+                rec = Some(temp_rec);
+            }
+        }
+        rec
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        if !self.infinite.is_empty() {
+            return None;
+        }
+        self.root.as_ref().map(|root| root.bounds.bbox)
+    }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        let mut sum = 0.0;
+        if let Some(root) = &self.root {
+            sum += root.pdf_value(&self.lights, o, v, 1.0 - self.infinite_prob);
+        }
+        for (i, light) in self.infinite.iter().enumerate() {
+            let weight = self.infinite_prob * self.infinite_distribution.discrete_pdf(i);
+            if weight > 0.0 {
+                sum += weight * self.lights[*light].pdf_value(o, v);
+            }
+        }
+        sum
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        match &self.root {
+            Some(root) if random_double_default() >= self.infinite_prob => {
+                self.lights[root.sample(o)].random(o)
+            }
+            _ => {
+                let (i, _) = self
+                    .infinite_distribution
+                    .sample_discrete(random_double_default());
+                self.lights[self.infinite[i]].random(o)
+            }
+        }
+    }
+    fn power(&self) -> f64 {
+        self.total_power
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f64, z0: f64, k: f64, radiance: f64) -> Arc<dyn Hittable> {
+        let c = Color {
+            x: radiance,
+            y: radiance,
+            z: radiance,
+        };
+        Arc::new(XZRect::new(
+            x0,
+            x0 + 1.0,
+            z0,
+            z0 + 1.0,
+            k,
+            DiffuseLight::new(c),
+        ))
+    }
+
+    #[test]
+    fn pdf_matches_sampling() {
+        let mut list = LightList::new();
+        list.add(square(-3.0, -0.5, 2.0, 1.0));
+        list.add(square(-0.5, -0.5, 3.0, 4.0));
+        list.add(square(2.0, -0.5, 1.0, 2.0));
+        list.add(square(0.5, 2.0, 4.0, 8.0));
+        let lights = list.objects.clone();
+        let bvh = LightBvh::new(list);
+        let o = Point3::new();
+
+        // over directions that reach a light, E[1 / pdf] is its solid angle
+        let n = 200000;
+        let mut estimates = vec![0.0; lights.len()];
+        for _ in 0..n {
+            let v = bvh.random(o);
+            let pdf = bvh.pdf_value(o, v);
+            assert!(pdf > 0.0);
+            for (estimate, light) in estimates.iter_mut().zip(lights.iter()) {
+                if light.hit(&Ray::new(o, v), 0.001, INFINITY).is_some() {
+                    *estimate += 1.0 / pdf / n as f64;
+                }
+            }
+        }
+        for (estimate, light) in estimates.iter().zip(lights.iter()) {
+            let bbox = light.bounding_box(0.0, 1.0).unwrap();
+            let corner = Point3 {
+                y: (bbox.minimum.y + bbox.maximum.y) / 2.0,
+                ..bbox.minimum
+            };
+            let ex = Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            };
+            let ey = Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            };
+            let solid_angle = SphericalRectangle::new(o, corner, ex, ey).solid_angle;
+            assert!((estimate / solid_angle - 1.0).abs() < 0.03);
+        }
+    }
+}
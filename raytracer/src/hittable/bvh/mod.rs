@@ -4,7 +4,9 @@ use std::cmp::Ordering;
 use crate::*;
 
 pub mod aabb;
+pub mod light_bvh;
 pub use aabb::*;
+pub use light_bvh::*;
 pub struct BvhNode {
     left: Arc<dyn Hittable>,
     right: Arc<dyn Hittable>,
@@ -41,6 +43,15 @@ impl Hittable for BvhNode {
         }
         res
     }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if self.box_.hit(r, t_min, t_max).is_none() {
+            return 1.0;
+        }
+        match self.left.transmittance(r, t_min, t_max) {
+            t if t <= 0.0 => 0.0,
+            t => t * self.right.transmittance(r, t_min, t_max),
+        }
+    }
 }
 
 impl BvhNode {
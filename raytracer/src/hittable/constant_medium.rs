@@ -26,6 +26,35 @@ impl<H: Hittable> ConstantMedium<H, Isotropic<SolidColor>> {
     }
 }
 
+impl<H: Hittable, T: Texture> ConstantMedium<H, HenyeyGreenstein<T>> {
+    // medium with an anisotropic phase function, g in (-1, 1)
+    pub fn new_hg(b: H, d: f64, a: T, g: f64) -> Self {
+        ConstantMedium::new_phase(b, d, HenyeyGreenstein::new(a, g))
+    }
+}
+
+impl<H: Hittable> ConstantMedium<H, EmissiveVolume<Isotropic<SolidColor>>> {
+    // glowing medium, `emission` being the radiance emitted per unit length
+    pub fn new_emissive(b: H, d: f64, c: Color, emission: Arc<dyn Texture>) -> Self {
+        let phase = Isotropic::new_color(c);
+        ConstantMedium::new_phase(
+            b,
+            d,
+            EmissiveVolume::new(phase, emission, constant_texture(d)),
+        )
+    }
+}
+
+impl<H: Hittable, M: Material> ConstantMedium<H, M> {
+    pub fn new_phase(b: H, d: f64, phase_function: M) -> Self {
+        ConstantMedium {
+            boundary: b,
+            phase_function,
+            neg_inv_density: -1.0 / d,
+        }
+    }
+}
+
 impl<H: Hittable, M: Material> Hittable for ConstantMedium<H, M> {
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
         self.boundary.bounding_box(time0, time1)
@@ -65,4 +94,20 @@ impl<H: Hittable, M: Material> Hittable for ConstantMedium<H, M> {
 
         Some(HitRecord::new(t, p, &self.phase_function))
     }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let rec1 = match self.boundary.hit(r, -INFINITY, INFINITY) {
+            None => return 1.0,
+            Some(rec) => rec,
+        };
+        let rec2 = match self.boundary.hit(r, rec1.t + 0.0001, INFINITY) {
+            None => return 1.0,
+            Some(rec) => rec,
+        };
+        let t0 = rec1.t.max(t_min).max(0.0);
+        let t1 = rec2.t.min(t_max);
+        if t0 >= t1 {
+            return 1.0;
+        }
+        ((t1 - t0) * r.dir.length() / self.neg_inv_density).exp()
+    }
 }
@@ -0,0 +1,123 @@
+use crate::*;
+
+// Scene-wide participating medium that needs no boundary geometry. Its
+// density is `density` at `base_height` and falls off as
+// exp(-falloff * (y - base)), so a falloff of zero is uniform fog. It fills
+// a sphere of `radius` around `center` (the origin unless moved with
+// with_center), beyond which rays escape to the background. Free flights
+// are sampled by inverting the optical depth in closed form.
+pub struct Fog {
+    pub density: f64,
+    pub base_height: f64,
+    pub falloff: f64,
+    pub center: Point3,
+    pub radius: f64,
+    pub phase_function: HenyeyGreenstein<SolidColor>,
+}
+
+impl Fog {
+    pub fn new(density: f64, albedo: Color, g: f64, radius: f64) -> Self {
+        Self::new_height(density, albedo, g, 0.0, 0.0, radius)
+    }
+    pub fn new_height(
+        density: f64,
+        albedo: Color,
+        g: f64,
+        base_height: f64,
+        falloff: f64,
+        radius: f64,
+    ) -> Self {
+        Fog {
+            density,
+            base_height,
+            falloff,
+            center: Point3::new(),
+            radius,
+            phase_function: HenyeyGreenstein::new_color(albedo, g),
+        }
+    }
+    pub fn with_center(mut self, center: Point3) -> Self {
+        self.center = center;
+        self
+    }
+
+    // part of [t_min, t_max] along r that lies inside the fog
+    fn interval(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let oc = r.orig - self.center;
+        let a = r.dir.length_squared();
+        let half_b = dot(oc, r.dir);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return None;
+        }
+        let sqrtd = discriminant.sqrt();
+        let t0 = t_min.max((-half_b - sqrtd) / a);
+        let t1 = t_max.min((-half_b + sqrtd) / a);
+        if t0 < t1 {
+            Some((t0, t1))
+        } else {
+            None
+        }
+    }
+
+    fn density_at(&self, y: f64) -> f64 {
+        self.density * (-self.falloff * (y - self.base_height)).exp()
+    }
+
+    // k * dy / ds along the ray, the rate the log density changes with distance
+    fn rate(&self, r: &Ray) -> f64 {
+        self.falloff * r.dir.y / r.dir.length()
+    }
+
+    // optical depth from the origin of r to a distance s along it
+    fn optical_depth(&self, r: &Ray, s: f64) -> f64 {
+        let sigma0 = self.density_at(r.orig.y);
+        let rate = self.rate(r);
+        if rate.abs() < 1e-9 {
+            sigma0 * s
+        } else {
+            sigma0 * (1.0 - (-rate * s).exp()) / rate
+        }
+    }
+
+    // scattering event along r before t_max, if there is one
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t0, t1) = self.interval(r, t_min, t_max)?;
+        // free flights start where the ray enters the fog
+        let entry = Ray {
+            orig: r.at(t0),
+            ..*r
+        };
+        let sigma0 = self.density_at(entry.orig.y);
+        if sigma0 <= 0.0 {
+            return None;
+        }
+        let tau = -(1.0 - random_double_default()).ln();
+        let rate = self.rate(&entry);
+        let s = if rate.abs() < 1e-9 {
+            tau / sigma0
+        } else {
+            let x = 1.0 - tau * rate / sigma0;
+            // heading up into thinning fog the total depth can fall short
+            if x <= 0.0 {
+                return None;
+            }
+            -x.ln() / rate
+        };
+        let t = t0 + s / r.dir.length();
+        if t >= t1 {
+            return None;
+        }
+        Some(HitRecord::new(t, r.at(t), &self.phase_function))
+    }
+
+    pub fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let (t_min, t_max) = match self.interval(r, t_min, t_max) {
+            Some(interval) => interval,
+            None => return 1.0,
+        };
+        let length = r.dir.length();
+        (self.optical_depth(r, t_min * length) - self.optical_depth(r, t_max * length)).exp()
+    }
+}
@@ -0,0 +1,100 @@
+use crate::*;
+
+// Medium whose density is the first channel of a 3D texture, bounded above
+// by `majorant`. Free flights are sampled with delta tracking and shadow
+// rays are attenuated with ratio tracking (Novak et al. 2014).
+pub struct HeterogeneousMedium<H: Hittable, T: Texture, M: Material> {
+    pub boundary: H,
+    pub density: T,
+    pub majorant: f64,
+    pub phase_function: M,
+}
+
+impl<H: Hittable, T: Texture> HeterogeneousMedium<H, T, Isotropic<SolidColor>> {
+    pub fn new(b: H, density: T, majorant: f64, c: Color) -> Self {
+        HeterogeneousMedium::new_phase(b, density, majorant, Isotropic::new_color(c))
+    }
+}
+
+impl<H: Hittable> HeterogeneousMedium<H, Arc<dyn Texture>, EmissiveVolume<Isotropic<SolidColor>>> {
+    // glowing medium, `emission` being the radiance emitted per unit length
+    pub fn new_emissive(
+        b: H,
+        density: Arc<dyn Texture>,
+        majorant: f64,
+        c: Color,
+        emission: Arc<dyn Texture>,
+    ) -> Self {
+        let phase = EmissiveVolume::new(Isotropic::new_color(c), emission, density.clone());
+        HeterogeneousMedium::new_phase(b, density, majorant, phase)
+    }
+}
+
+impl<H: Hittable, T: Texture, M: Material> HeterogeneousMedium<H, T, M> {
+    pub fn new_phase(b: H, density: T, majorant: f64, phase_function: M) -> Self {
+        HeterogeneousMedium {
+            boundary: b,
+            density,
+            majorant,
+            phase_function,
+        }
+    }
+
+    fn density_at(&self, p: Point3) -> f64 {
+        clamp(self.density.value(0.0, 0.0, p).x, 0.0, self.majorant)
+    }
+
+    // part of the ray inside the boundary, clipped to [t_min, t_max]
+    fn interval(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let rec1 = self.boundary.hit(r, -INFINITY, INFINITY)?;
+        let rec2 = self.boundary.hit(r, rec1.t + 0.0001, INFINITY)?;
+        let t0 = rec1.t.max(t_min).max(0.0);
+        let t1 = rec2.t.min(t_max);
+        if t0 >= t1 || self.majorant <= 0.0 {
+            return None;
+        }
+        Some((t0, t1))
+    }
+
+    // distance in t to the next tentative collision
+    fn step(&self, r: &Ray) -> f64 {
+        -(1.0 - random_double_default()).ln() / (self.majorant * r.dir.length())
+    }
+}
+
+impl<H: Hittable, T: Texture, M: Material> Hittable for HeterogeneousMedium<H, T, M> {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(time0, time1)
+    }
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (mut t, t1) = self.interval(r, t_min, t_max)?;
+        loop {
+            t += self.step(r);
+            if t >= t1 {
+                return None;
+            }
+            let p = r.at(t);
+            // a real collision, otherwise a null one that continues the flight
+            if random_double_default() * self.majorant < self.density_at(p) {
+                return Some(HitRecord::new(t, p, &self.phase_function));
+            }
+        }
+    }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let (mut t, t1) = match self.interval(r, t_min, t_max) {
+            Some(interval) => interval,
+            None => return 1.0,
+        };
+        let mut transmittance = 1.0;
+        loop {
+            t += self.step(r);
+            if t >= t1 {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density_at(r.at(t)) / self.majorant;
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::*;
+
+// A closed surface filled with a homogeneous medium. The surface keeps its
+// own material; a ray is inside when the next surface it meets faces away
+// from it, so only light that got through the surface, by refraction for
+// instance, travels through the medium, and it leaves through the surface
+// again.
+pub struct InteriorMedium<H: Hittable, M: Material> {
+    pub boundary: H,
+    pub phase_function: M,
+    pub neg_inv_density: f64,
+}
+
+impl<H: Hittable> InteriorMedium<H, Isotropic<SolidColor>> {
+    pub fn new(b: H, d: f64, c: Color) -> Self {
+        InteriorMedium::new_phase(b, d, Isotropic::new_color(c))
+    }
+}
+
+impl<H: Hittable, M: Material> InteriorMedium<H, M> {
+    pub fn new_phase(b: H, d: f64, phase_function: M) -> Self {
+        InteriorMedium {
+            boundary: b,
+            phase_function,
+            neg_inv_density: -1.0 / d,
+        }
+    }
+}
+
+impl<H: Hittable, M: Material> Hittable for InteriorMedium<H, M> {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(time0, time1)
+    }
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let surface = self.boundary.hit(r, t_min, INFINITY);
+        if let Some(exit) = surface.as_ref().filter(|rec| !rec.front_face) {
+            let hit_distance =
+                self.neg_inv_density * random_double_default().log(std::f64::consts::E);
+            let t = t_min + hit_distance / r.dir.length();
+            if t < exit.t.min(t_max) {
+                return Some(HitRecord::new(t, r.at(t), &self.phase_function));
+            }
+        }
+        surface.filter(|rec| rec.t <= t_max)
+    }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        match self.boundary.hit(r, t_min, INFINITY) {
+            Some(rec) if rec.t <= t_max => 0.0,
+            Some(rec) if !rec.front_face => {
+                ((t_max - t_min) * r.dir.length() / self.neg_inv_density).exp()
+            }
+            _ => 1.0,
+        }
+    }
+}
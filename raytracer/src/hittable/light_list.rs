@@ -0,0 +1,199 @@
+use crate::*;
+
+// A list of lights that picks one in proportion to its emitted power.
+#[derive(Clone)]
+pub struct LightList {
+    pub objects: Vec<Arc<dyn Hittable>>,
+    pub powers: Vec<f64>,
+    distribution: Distribution1D,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        LightList {
+            objects: Vec::new(),
+            powers: Vec::new(),
+            distribution: Distribution1D::new(&[]),
+        }
+    }
+
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        let power = object.power();
+        self.add_with_power(object, power);
+    }
+
+    pub fn add_with_power(&mut self, object: Arc<dyn Hittable>, power: f64) {
+        self.objects.push(object);
+        self.powers.push(power);
+        // rebuilt on every light, scenes only add them while being set up
+        self.distribution = Distribution1D::new(&self.powers);
+    }
+
+    // Lights are picked by their power times how much more than on average
+    // they send towards o, which only differs from the power for lights with
+    // an angular profile. The distribution covers a total of zero.
+    fn weight(&self, index: usize, o: Point3) -> f64 {
+        self.powers[index] * self.objects[index].emission_weight(o)
+    }
+
+    fn total_weight(&self, o: Point3) -> f64 {
+        (0..self.objects.len()).map(|i| self.weight(i, o)).sum()
+    }
+
+    pub fn selection_pdf(&self, index: usize, o: Point3) -> f64 {
+        let total = self.total_weight(o);
+        if total > 0.0 {
+            self.weight(index, o) / total
+        } else {
+            self.distribution.discrete_pdf(index)
+        }
+    }
+}
+
+impl Hittable for LightList {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest_so_far = t_max;
+        let mut rec: Option<HitRecord> = None;
+
+        for object in &self.objects {
+            if let Some(temp_rec) = object.hit(r, t_min, closest_so_far) {
+                closest_so_far = temp_rec.t;
+                rec = Some(temp_rec);
+            }
+        }
+        rec
+    }
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        let mut output_box: Option<AABB> = None;
+        for object in &self.objects {
+            let temp_box = object.bounding_box(time0, time1)?;
+            output_box = match output_box {
+                None => Some(temp_box),
+                Some(last_box) => Some(surrounding_box(&temp_box, &last_box)),
+            }
+        }
+        output_box
+    }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        let total = self.total_weight(o);
+        let mut sum = 0.0;
+        for (i, object) in self.objects.iter().enumerate() {
+            let weight = if total > 0.0 {
+                self.weight(i, o) / total
+            } else {
+                self.distribution.discrete_pdf(i)
+            };
+            if weight > 0.0 {
+                sum += weight * object.pdf_value(o, v);
+            }
+        }
+        sum
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        let total = self.total_weight(o);
+        if total <= 0.0 {
+            let (index, _) = self.distribution.sample_discrete(random_double_default());
+            return self.objects[index].random(o);
+        }
+        let mut u = random_double_default() * total;
+        let mut index = 0;
+        for i in 0..self.objects.len() {
+            let weight = self.weight(i, o);
+            if weight > 0.0 {
+                index = i;
+                if u < weight {
+                    break;
+                }
+                u -= weight;
+            }
+        }
+        self.objects[index].random(o)
+    }
+    fn power(&self) -> f64 {
+        self.powers.iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rotationally symmetric fixture peaked straight down the nadir
+    const SPOT: &str = "TILT=NONE
+1 1000 1 3 1 1 1 0 0 0
+1 1 100
+0 45 90
+0
+100 10 0
+";
+
+    fn square(x0: f64) -> Arc<dyn Hittable> {
+        let profile = Arc::new(IesProfile::parse(SPOT).unwrap());
+        let tangent = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        Arc::new(XZRect::new(
+            x0,
+            x0 + 1.0,
+            -0.5,
+            0.5,
+            2.0,
+            DiffuseLight::new(Color {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            })
+            .with_two_sided(true)
+            .with_profile(profile, tangent),
+        ))
+    }
+
+    #[test]
+    fn profiles_weigh_selection_towards_the_point() {
+        let mut list = LightList::new();
+        list.add(square(-0.5));
+        list.add(square(3.0));
+        let o = Point3::new();
+        // same power, but the second one is seen far off its nadir
+        assert!((list.powers[0] - list.powers[1]).abs() < 1e-9);
+        assert!(list.selection_pdf(1, o) < 0.2 * list.selection_pdf(0, o));
+
+        // over directions that reach a light, E[1 / pdf] is its solid angle
+        let n = 200000;
+        let mut estimates = [0.0; 2];
+        for _ in 0..n {
+            let v = list.random(o);
+            let pdf = list.pdf_value(o, v);
+            assert!(pdf > 0.0);
+            for (estimate, light) in estimates.iter_mut().zip(list.objects.iter()) {
+                if light.hit(&Ray::new(o, v), 0.001, INFINITY).is_some() {
+                    *estimate += 1.0 / pdf / n as f64;
+                }
+            }
+        }
+        for (estimate, x0) in estimates.iter().zip([-0.5, 3.0]) {
+            let solid_angle = SphericalRectangle::new(
+                o,
+                Point3 {
+                    x: x0,
+                    y: 2.0,
+                    z: -0.5,
+                },
+                Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vec3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+            )
+            .solid_angle;
+            assert!((estimate / solid_angle - 1.0).abs() < 0.03);
+        }
+    }
+}
@@ -4,18 +4,30 @@ pub mod aabox;
 pub mod aarect;
 pub mod bvh;
 pub mod constant_medium;
+pub mod fog;
+pub mod heterogeneous_medium;
 pub mod image_box;
+pub mod interior_medium;
+pub mod light_list;
 pub mod moving_sphere;
+pub mod quad;
 pub mod sphere;
+pub mod subsurface;
 pub mod triangle;
 
 pub use aabox::*;
 pub use aarect::*;
 pub use bvh::*;
 pub use constant_medium::*;
+pub use fog::*;
+pub use heterogeneous_medium::*;
 pub use image_box::*;
+pub use interior_medium::*;
+pub use light_list::*;
 pub use moving_sphere::*;
+pub use quad::*;
 pub use sphere::*;
+pub use subsurface::*;
 pub use triangle::*;
 
 pub use std::sync::Arc;
@@ -32,6 +44,53 @@ pub trait Hittable: Sync + Send {
             z: 0.0,
         }
     }
+    fn power(&self) -> f64 {
+        0.0
+    }
+    // unsigned normal of a planar emitter, None if it emits in every direction
+    fn emission_normal(&self) -> Option<Vec3> {
+        None
+    }
+    // how much more than its power suggests the emitter sends towards o
+    fn emission_weight(&self, _o: Point3) -> f64 {
+        1.0
+    }
+    // fraction of light that passes along r between t_min and t_max, used
+    // for shadow rays; surfaces block it entirely
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if self.hit(r, t_min, t_max).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.as_ref().hit(r, t_min, t_max)
+    }
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.as_ref().pdf_value(o, v)
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.as_ref().random(o)
+    }
+    fn power(&self) -> f64 {
+        self.as_ref().power()
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        self.as_ref().emission_normal()
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        self.as_ref().emission_weight(o)
+    }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        self.as_ref().transmittance(r, t_min, t_max)
+    }
 }
 
 #[derive(Clone)]
@@ -96,7 +155,7 @@ impl<H: Hittable> Translate<H> {
 
 impl<H: Hittable> Hittable for Translate<H> {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let moved_r = Ray::new_tm(r.orig - self.offset, r.dir, r.tm);
+        let moved_r = r.scattered(r.orig - self.offset, r.dir);
         match self.ptr.hit(&moved_r, t_min, t_max) {
             None => None,
             Some(mut rec) => {
@@ -114,6 +173,21 @@ impl<H: Hittable> Hittable for Translate<H> {
             )
         })
     }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(o - self.offset, v)
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.ptr.random(o - self.offset)
+    }
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        self.ptr.emission_normal()
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        self.ptr.emission_weight(o - self.offset)
+    }
 }
 
 pub struct RotateY<H: Hittable> {
@@ -176,6 +250,20 @@ impl<H: Hittable> RotateY<H> {
             bbox: Some(AABB::new(min, max)),
         }
     }
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: v.x * self.cos_theta - v.z * self.sin_theta,
+            y: v.y,
+            z: v.x * self.sin_theta + v.z * self.cos_theta,
+        }
+    }
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.cos_theta * v.x + self.sin_theta * v.z,
+            y: v.y,
+            z: -self.sin_theta * v.x + self.cos_theta * v.z,
+        }
+    }
 }
 
 impl<H: Hittable> Hittable for RotateY<H> {
@@ -193,7 +281,7 @@ impl<H: Hittable> Hittable for RotateY<H> {
             y: r.dir.y,
             z: r.dir.x * self.sin_theta + r.dir.z * self.cos_theta,
         };
-        let rotated_r = Ray::new_tm(origin, direction, r.tm);
+        let rotated_r = r.scattered(origin, direction);
 
         match self.ptr.hit(&rotated_r, t_min, t_max) {
             None => None,
@@ -214,6 +302,22 @@ impl<H: Hittable> Hittable for RotateY<H> {
             }
         }
     }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(self.to_object(o), self.to_object(v))
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.to_world(self.ptr.random(self.to_object(o)))
+    }
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        self.ptr.emission_normal().map(|n| self.to_world(n))
+    }
+    // the profile tangent is taken in object space here
+    fn emission_weight(&self, o: Point3) -> f64 {
+        self.ptr.emission_weight(self.to_object(o))
+    }
 }
 
 pub struct FlipFace<H: Hittable> {
@@ -239,6 +343,33 @@ impl<H: Hittable> Hittable for FlipFace<H> {
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
         self.ptr.bounding_box(time0, time1)
     }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(o, v)
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.ptr.random(o)
+    }
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        self.ptr.emission_normal()
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        self.ptr.emission_weight(o)
+    }
+}
+
+// emission_weight of a flat emitter, judged from the direction o is seen in
+// from its centre
+pub fn flat_emission_weight(mat: &dyn Material, center: Point3, normal: Vec3, o: Point3) -> f64 {
+    let dir = unit_vector(o - center);
+    let normal = if dot(normal, dir) < 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    mat.emission_weight(normal, dir)
 }
 
 #[derive(Clone)]
@@ -278,6 +409,16 @@ impl Hittable for HittableList {
         }
         rec
     }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(r, t_min, t_max);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
         if self.objects.is_empty() {
             return None;
@@ -310,4 +451,7 @@ impl Hittable for HittableList {
         let int_size = self.objects.len() as i32;
         self.objects[random_int(0, int_size - 1) as usize].random(o)
     }
+    fn power(&self) -> f64 {
+        self.objects.iter().map(|object| object.power()).sum()
+    }
 }
@@ -0,0 +1,95 @@
+use crate::*;
+
+// Parallelogram q + a*u + b*v for a, b in [0, 1].
+pub struct Quad<M: Material> {
+    pub q: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub mat: M,
+    pub normal: Vec3,
+    pub d: f64,
+    pub w: Vec3,
+    pub area: f64,
+    pub bbox: AABB,
+}
+
+impl<M: Material> Quad<M> {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mut mat: M) -> Self {
+        let n = u ^ v;
+        mat.resolve_area(n.length());
+        let normal = unit_vector(n);
+        let pad = Vec3 {
+            x: 0.0001,
+            y: 0.0001,
+            z: 0.0001,
+        };
+        let mut min = q;
+        let mut max = q;
+        for p in [q + u, q + v, q + u + v] {
+            min.x = min.x.min(p.x);
+            min.y = min.y.min(p.y);
+            min.z = min.z.min(p.z);
+            max.x = max.x.max(p.x);
+            max.y = max.y.max(p.y);
+            max.z = max.z.max(p.z);
+        }
+        Quad {
+            q,
+            u,
+            v,
+            mat,
+            normal,
+            d: dot(normal, q),
+            w: n / dot(n, n),
+            area: n.length(),
+            bbox: AABB::new(min - pad, max + pad),
+        }
+    }
+}
+
+impl<M: Material> Hittable for Quad<M> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = dot(self.normal, r.dir);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = (self.d - dot(self.normal, r.orig)) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        let p = r.at(t);
+        let planar_hitpt = p - self.q;
+        let alpha = dot(self.w, planar_hitpt ^ self.v);
+        let beta = dot(self.w, self.u ^ planar_hitpt);
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        let mut rec = HitRecord::new(t, p, &self.mat);
+        rec.set_face_normal(r, self.normal);
+        rec.u = alpha;
+        rec.v = beta;
+        Some(rec)
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, v), 0.001, INFINITY) {
+            None => 0.0,
+            Some(rec) => rect_pdf_value(origin, self.q, self.u, self.v, v, rec.t),
+        }
+    }
+    fn random(&self, origin: Vec3) -> Vec3 {
+        rect_random(origin, self.q, self.u, self.v)
+    }
+    fn power(&self) -> f64 {
+        self.mat.emitted_power(self.area)
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        let center = self.q + (self.u + self.v) * 0.5;
+        flat_emission_weight(&self.mat, center, self.normal, o)
+    }
+}
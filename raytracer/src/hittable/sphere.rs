@@ -1,10 +1,12 @@
 use crate::*;
 
+// Built through Sphere::new only, so an emitter given in watts always sees
+// its area.
 #[derive(Clone)]
 pub struct Sphere<M: Material> {
-    pub center: Vec3,
-    pub radius: f64,
-    pub mat_ptr: M,
+    center: Vec3,
+    radius: f64,
+    mat_ptr: M,
 }
 
 impl<M: Material> Sphere<M> {
@@ -14,6 +16,14 @@ impl<M: Material> Sphere<M> {
     //         radius: 0.0,
     //     }
     // }
+    pub fn new(center: Point3, radius: f64, mut mat_ptr: M) -> Self {
+        mat_ptr.resolve_area(4.0 * PI * radius * radius);
+        Sphere {
+            center,
+            radius,
+            mat_ptr,
+        }
+    }
     fn get_sphere_uv(p: Point3) -> (f64, f64) {
         // p: a given point on the sphere of radius one, centered at the origin.
         // u: returned value [0,1] of angle around the Y axis from X=-1.
@@ -77,10 +87,18 @@ impl<M: Material> Hittable for Sphere<M> {
     fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
         match self.hit(&Ray::new(o, v), 0.001, INFINITY) {
             None => 0.0,
-            Some(_) => {
-                let cos_theta_max =
-                    (1.0 - self.radius * self.radius / (self.center - o).length_squared()).sqrt();
-                let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+            Some(rec) => {
+                let distance_squared = (self.center - o).length_squared();
+                if distance_squared <= self.radius * self.radius {
+                    // inside the sphere the cone is undefined, sample the area
+                    let area = 4.0 * PI * self.radius * self.radius;
+                    let cosine = (dot(v, rec.normal) / v.length()).abs();
+                    return rec.t * rec.t * v.length_squared() / (cosine * area);
+                }
+                // 1 - cos(theta_max), written to stay accurate for distant spheres
+                let sin2_theta_max = self.radius * self.radius / distance_squared;
+                let one_minus_cos = sin2_theta_max / (1.0 + (1.0 - sin2_theta_max).sqrt());
+                let solid_angle = 2.0 * PI * one_minus_cos;
                 1.0 / solid_angle
             }
         }
@@ -89,7 +107,14 @@ impl<M: Material> Hittable for Sphere<M> {
         //println!("!!!!!\n");
         let direction = self.center - o;
         let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return self.center + random_unit_vector() * self.radius - o;
+        }
         let uvw = ONB::build_from_w(direction);
         uvw.local_vec(random_to_sphere(self.radius, distance_squared))
     }
+    fn power(&self) -> f64 {
+        self.mat_ptr
+            .emitted_power(4.0 * PI * self.radius * self.radius)
+    }
 }
@@ -0,0 +1,79 @@
+use crate::*;
+
+// Random-walk subsurface scattering inside a closed boundary: an
+// InteriorMedium whose surface is a smooth dielectric. Rays that refract in
+// take exponential free flights with the given mean free path and scatter
+// off the phase function until they leave through the surface again.
+pub struct Subsurface<H: Hittable, T: Texture> {
+    interior: InteriorMedium<DielectricSurface<H>, Isotropic<T>>,
+}
+
+// the boundary with its own material swapped for a dielectric
+struct DielectricSurface<H: Hittable> {
+    boundary: H,
+    surface: Dielectric,
+}
+
+impl<H: Hittable> Hittable for DielectricSurface<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.boundary.hit(r, t_min, t_max).map(|rec| HitRecord {
+            mat_ptr: &self.surface,
+            ..rec
+        })
+    }
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+// Inverts van de Hulst's multiple-scattering relation, so the colour a thick
+// slab shows is close to `albedo` (Chiang et al. 2016).
+pub fn single_scattering_albedo(albedo: Color) -> Color {
+    let invert = |a: f64| {
+        let a = clamp(a, 0.0, 0.999);
+        let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+        1.0 - s * s
+    };
+    Color {
+        x: invert(albedo.x),
+        y: invert(albedo.y),
+        z: invert(albedo.z),
+    }
+}
+
+impl<H: Hittable> Subsurface<H, SolidColor> {
+    // `albedo` is the multiple-scattering colour of the material
+    pub fn new(b: H, index_of_refraction: f64, albedo: Color, mean_free_path: f64) -> Self {
+        Subsurface::new_texture(
+            b,
+            index_of_refraction,
+            SolidColor::new(single_scattering_albedo(albedo)),
+            mean_free_path,
+        )
+    }
+}
+
+impl<H: Hittable, T: Texture> Subsurface<H, T> {
+    // `a` is the single-scattering albedo at each point of the volume
+    pub fn new_texture(b: H, index_of_refraction: f64, a: T, mean_free_path: f64) -> Self {
+        let surface = DielectricSurface {
+            boundary: b,
+            surface: Dielectric::new(index_of_refraction),
+        };
+        Subsurface {
+            interior: InteriorMedium::new_phase(surface, 1.0 / mean_free_path, Isotropic::new(a)),
+        }
+    }
+}
+
+impl<H: Hittable, T: Texture> Hittable for Subsurface<H, T> {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.interior.bounding_box(time0, time1)
+    }
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.interior.hit(r, t_min, t_max)
+    }
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> f64 {
+        self.interior.transmittance(r, t_min, t_max)
+    }
+}
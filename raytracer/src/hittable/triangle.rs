@@ -11,13 +11,14 @@ pub struct Triangle<M: Material> {
     pub uva: Vec3,
     pub uvab: Vec3,
     pub uvac: Vec3,
+    pub area: f64,
 }
 impl<M: Material> Triangle<M> {
     pub fn new(
         a: Point3,
         b: Point3,
         c: Point3,
-        mat: M,
+        mut mat: M,
         (ua, va): (f64, f64),
         (ub, vb): (f64, f64),
         (uc, vc): (f64, f64),
@@ -27,6 +28,7 @@ impl<M: Material> Triangle<M> {
         let normal_ = ab ^ ac;
         let n = unit_vector(normal_);
         let det = normal_.length();
+        mat.resolve_area(det / 2.0);
         let mut min = Point3::default();
         let mut max = Point3::default();
         // for i in 0..3 {
@@ -67,6 +69,7 @@ impl<M: Material> Triangle<M> {
             uva,
             uvab,
             uvac,
+            area: det / 2.0,
         }
     }
 }
@@ -94,6 +97,16 @@ impl<M: Material> Hittable for Triangle<M> {
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
         Some(self.bbox)
     }
+    fn power(&self) -> f64 {
+        self.mat.emitted_power(self.area)
+    }
+    fn emission_normal(&self) -> Option<Vec3> {
+        Some(self.n)
+    }
+    fn emission_weight(&self, o: Point3) -> f64 {
+        let center = (self.bbox.minimum + self.bbox.maximum) * 0.5;
+        flat_emission_weight(&self.mat, center, self.n, o)
+    }
 }
 
 // pub struct Triangle<M: Material> {
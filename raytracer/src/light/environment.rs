@@ -0,0 +1,206 @@
+use crate::*;
+
+use image::codecs::hdr::HdrDecoder;
+use image::GenericImageView;
+use std::io::BufReader;
+
+// Equirectangular mapping in the same convention as Sphere::get_sphere_uv,
+// except that v is measured from the top row of the image (Y=+1) down.
+pub fn direction_to_equirect(d: Vec3) -> (f64, f64) {
+    let d = unit_vector(d);
+    let theta = clamp(-d.y, -1.0, 1.0).acos();
+    let phi = (-d.z).atan2(d.x) + PI;
+    (phi / (2.0 * PI), 1.0 - theta / PI)
+}
+
+pub fn equirect_to_direction(u: f64, v: f64) -> Vec3 {
+    let phi = 2.0 * PI * u;
+    let theta = PI * (1.0 - v);
+    Vec3 {
+        x: -phi.cos() * theta.sin(),
+        y: -theta.cos(),
+        z: phi.sin() * theta.sin(),
+    }
+}
+
+// Radiance is stored linearly, row 0 at the top of the image.
+pub fn load_radiance_map(filename: &str) -> (Vec<Color>, usize, usize) {
+    let is_hdr = std::path::Path::new(filename)
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("hdr"));
+    if is_hdr {
+        let file = File::open(filename).expect("Cannot open.");
+        let decoder = HdrDecoder::new(BufReader::new(file)).expect("Cannot decode.");
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr().expect("Cannot decode.");
+        let data = pixels
+            .iter()
+            .map(|pixel| Color {
+                x: pixel[0] as f64,
+                y: pixel[1] as f64,
+                z: pixel[2] as f64,
+            })
+            .collect();
+        (data, metadata.width as usize, metadata.height as usize)
+    } else {
+        let image = image::open(filename).expect("Cannot open.");
+        let (width, height) = image.dimensions();
+        let color_scale = 1.0 / 255.0;
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                let pixel = image.get_pixel(i, j);
+                data.push(Color {
+                    x: pixel[0] as f64 * color_scale,
+                    y: pixel[1] as f64 * color_scale,
+                    z: pixel[2] as f64 * color_scale,
+                });
+            }
+        }
+        (data, width as usize, height as usize)
+    }
+}
+
+// Builds the sampling distribution of an equirectangular map from per-texel
+// luminance, weighted by sin(theta) to undo the stretching near the poles.
+pub fn equirect_distribution(
+    width: usize,
+    height: usize,
+    luminance_at: impl Fn(usize, usize) -> f64,
+) -> Distribution2D {
+    let mut func = Vec::with_capacity(width * height);
+    for j in 0..height {
+        let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+        for i in 0..width {
+            func.push(luminance_at(i, j) * sin_theta);
+        }
+    }
+    Distribution2D::new(&func, width, height)
+}
+
+// Converts a density over the equirectangular square into one over solid angle.
+pub fn equirect_pdf(distribution: &Distribution2D, d: Vec3) -> f64 {
+    let d = unit_vector(d);
+    let sin_theta = (1.0 - d.y * d.y).max(0.0).sqrt();
+    if sin_theta == 0.0 {
+        return 0.0;
+    }
+    let (u, v) = direction_to_equirect(d);
+    distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+}
+
+// Radiance integrated over the sphere, for a distribution built by
+// equirect_distribution.
+pub fn equirect_integral(distribution: &Distribution2D) -> f64 {
+    2.0 * PI * PI * distribution.marginal.func_int
+}
+
+// Distant light from an equirectangular image, rotated about the Y axis.
+// Works both as the background and as an entry of the light list. Its power
+// is what crosses a sphere of `scene_radius` around the scene, so set that
+// with with_scene_radius before adding it next to other emitters.
+pub struct EnvironmentLight {
+    pub data: Vec<Color>,
+    pub width: usize,
+    pub height: usize,
+    pub intensity: f64,
+    pub sin_theta: f64,
+    pub cos_theta: f64,
+    pub distribution: Distribution2D,
+    pub scene_radius: f64,
+    pub group: Option<String>,
+}
+
+impl EnvironmentLight {
+    pub fn new(filename: &str, intensity: f64, rotation: f64) -> Self {
+        let (data, width, height) = load_radiance_map(filename);
+        Self::new_data(data, width, height, intensity, rotation)
+    }
+
+    pub fn new_data(
+        data: Vec<Color>,
+        width: usize,
+        height: usize,
+        intensity: f64,
+        rotation: f64,
+    ) -> Self {
+        let radians = degrees_to_radians(rotation);
+        let distribution =
+            equirect_distribution(width, height, |i, j| luminance(data[j * width + i]));
+        EnvironmentLight {
+            data,
+            width,
+            height,
+            intensity,
+            sin_theta: radians.sin(),
+            cos_theta: radians.cos(),
+            distribution,
+            scene_radius: 0.0,
+            group: None,
+        }
+    }
+
+    pub fn with_scene_radius(mut self, radius: f64) -> Self {
+        self.scene_radius = radius;
+        self
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    fn to_map(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: v.x * self.cos_theta - v.z * self.sin_theta,
+            y: v.y,
+            z: v.x * self.sin_theta + v.z * self.cos_theta,
+        }
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.cos_theta * v.x + self.sin_theta * v.z,
+            y: v.y,
+            z: -self.sin_theta * v.x + self.cos_theta * v.z,
+        }
+    }
+
+    pub fn lookup(&self, u: f64, v: f64) -> Color {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.data[j * self.width + i]
+    }
+}
+
+impl Background for EnvironmentLight {
+    fn value(&self, dir: Vec3) -> Color {
+        let (u, v) = direction_to_equirect(self.to_map(dir));
+        self.lookup(u, v) * self.intensity
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+impl Hittable for EnvironmentLight {
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+    fn pdf_value(&self, _o: Point3, v: Vec3) -> f64 {
+        equirect_pdf(&self.distribution, self.to_map(v))
+    }
+    fn random(&self, _o: Vec3) -> Vec3 {
+        let ((u, v), _) = self
+            .distribution
+            .sample_continuous(random_double_default(), random_double_default());
+        self.to_world(equirect_to_direction(u, v))
+    }
+    fn power(&self) -> f64 {
+        let area = PI * self.scene_radius * self.scene_radius;
+        area * equirect_integral(&self.distribution) * self.intensity
+    }
+}
@@ -0,0 +1,57 @@
+use crate::*;
+
+use image::codecs::hdr::HdrEncoder;
+use image::Rgb;
+
+// Names of the light groups rendered into separate buffers. Group 0 is
+// "default" and collects every emitter without a listed group, so the
+// groups always add up to the beauty image.
+#[derive(Clone)]
+pub struct LightGroups {
+    pub names: Vec<String>,
+}
+
+impl LightGroups {
+    pub fn new(names: &[&str]) -> Self {
+        let mut all = vec!["default".to_string()];
+        for name in names {
+            if !all.iter().any(|n| n == name) {
+                all.push(name.to_string());
+            }
+        }
+        LightGroups { names: all }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn index(&self, group: Option<&str>) -> usize {
+        group
+            .and_then(|group| self.names.iter().position(|name| name == group))
+            .unwrap_or(0)
+    }
+
+    pub fn zero(&self) -> Vec<Color> {
+        vec![Color::new(); self.len()]
+    }
+}
+
+pub fn sum_groups(groups: &[Color]) -> Color {
+    groups.iter().fold(Color::new(), |sum, &c| sum + c)
+}
+
+// Writes a linear float buffer, row 0 at the top, as Radiance HDR.
+pub fn write_hdr(filename: &str, data: &[Color], width: usize, height: usize) {
+    let pixels: Vec<Rgb<f32>> = data
+        .iter()
+        .map(|c| {
+            let channel = |x: f64| if x.is_nan() { 0.0 } else { x.max(0.0) as f32 };
+            Rgb([channel(c.x), channel(c.y), channel(c.z)])
+        })
+        .collect();
+    let file = File::create(filename).expect("Cannot create.");
+    HdrEncoder::new(std::io::BufWriter::new(file))
+        .encode(&pixels, width, height)
+        .expect("Cannot encode.");
+}
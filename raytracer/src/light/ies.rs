@@ -0,0 +1,302 @@
+use crate::*;
+
+use std::io::Read;
+
+// Photometric web from an IES LM-63 file. Only type C photometry is
+// supported: vertical angle 0 points at the nadir of the fixture and the
+// horizontal angle turns around it.
+pub struct IesProfile {
+    pub vertical_angles: Vec<f64>,
+    pub horizontal_angles: Vec<f64>,
+    // candela[h][v], already scaled by the candela multiplier and ballast factor
+    pub candela: Vec<Vec<f64>>,
+    pub max_candela: f64,
+}
+
+// Index of the segment containing x and the position inside it.
+fn locate(angles: &[f64], x: f64) -> Option<(usize, f64)> {
+    let last = angles.len() - 1;
+    if x < angles[0] || x > angles[last] {
+        return None;
+    }
+    if last == 0 {
+        return Some((0, 0.0));
+    }
+    let i = angles.partition_point(|&a| a <= x).clamp(1, last) - 1;
+    let width = angles[i + 1] - angles[i];
+    let t = if width > 0.0 {
+        (x - angles[i]) / width
+    } else {
+        0.0
+    };
+    Some((i, t))
+}
+
+impl IesProfile {
+    pub fn new(filename: &str) -> IesProfile {
+        let mut text = String::new();
+        File::open(filename)
+            .expect("Cannot open.")
+            .read_to_string(&mut text)
+            .expect("Cannot read.");
+        IesProfile::parse(&text).expect("Cannot parse IES profile.")
+    }
+
+    pub fn parse(text: &str) -> Option<IesProfile> {
+        let mut lines = text.lines();
+        // keywords and comments up to the TILT line are ignored
+        let tilt = lines.find(|line| line.trim_start().starts_with("TILT="))?;
+        let mut tokens = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<f64>().ok());
+        let mut next = || tokens.next().flatten();
+
+        if tilt.trim() == "TILT=INCLUDE" {
+            let _geometry = next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()? as i32;
+        let _units = next()?;
+        let (_width, _length, _height) = (next()?, next()?, next()?);
+        let ballast_factor = next()?;
+        let _future_use = next()?;
+        let _input_watts = next()?;
+        if photometric_type != 1 || vertical_count == 0 || horizontal_count == 0 {
+            return None;
+        }
+
+        let mut vertical_angles = Vec::with_capacity(vertical_count);
+        for _ in 0..vertical_count {
+            vertical_angles.push(next()?);
+        }
+        let mut horizontal_angles = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            horizontal_angles.push(next()?);
+        }
+        let scale = multiplier * ballast_factor;
+        let mut candela = Vec::with_capacity(horizontal_count);
+        let mut max_candela: f64 = 0.0;
+        for _ in 0..horizontal_count {
+            let mut column = Vec::with_capacity(vertical_count);
+            for _ in 0..vertical_count {
+                let value = next()? * scale;
+                max_candela = max_candela.max(value);
+                column.push(value);
+            }
+            candela.push(column);
+        }
+
+        Some(IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        })
+    }
+
+    // Folds a horizontal angle in [0, 360) into the range the file covers.
+    fn fold_horizontal(&self, phi: f64) -> f64 {
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        if last == 0.0 {
+            0.0
+        } else if last == 90.0 {
+            let a = phi % 180.0;
+            if a > 90.0 {
+                180.0 - a
+            } else {
+                a
+            }
+        } else if last == 180.0 && phi > 180.0 {
+            360.0 - phi
+        } else {
+            phi
+        }
+    }
+
+    fn column_value(&self, h: usize, theta: f64) -> f64 {
+        match locate(&self.vertical_angles, theta) {
+            None => 0.0,
+            Some((i, _)) if i + 1 >= self.vertical_angles.len() => self.candela[h][i],
+            Some((i, t)) => self.candela[h][i] * (1.0 - t) + self.candela[h][i + 1] * t,
+        }
+    }
+
+    // Intensity in candela, angles in degrees.
+    pub fn candela(&self, theta: f64, phi: f64) -> f64 {
+        let phi = self.fold_horizontal(phi.rem_euclid(360.0));
+        match locate(&self.horizontal_angles, phi) {
+            None => 0.0,
+            Some((h, _)) if h + 1 >= self.horizontal_angles.len() => self.column_value(h, theta),
+            Some((h, t)) => {
+                self.column_value(h, theta) * (1.0 - t) + self.column_value(h + 1, theta) * t
+            }
+        }
+    }
+
+    // Intensity relative to the peak of the profile for a direction given
+    // in the fixture frame, z pointing at the nadir.
+    pub fn multiplier(&self, local: Vec3) -> f64 {
+        if self.max_candela <= 0.0 {
+            return 0.0;
+        }
+        let local = unit_vector(local);
+        let theta = clamp(local.z, -1.0, 1.0).acos().to_degrees();
+        let phi = local.y.atan2(local.x).to_degrees();
+        self.candela(theta, phi) / self.max_candela
+    }
+
+    pub fn multiplier_in(&self, frame: &ONB, dir: Vec3) -> f64 {
+        self.multiplier(frame.world_to_local(dir))
+    }
+
+    // Mean multiplier over the sphere, so a point light with this profile
+    // emits 4 pi * intensity * spherical_average() in total.
+    pub fn spherical_average(&self) -> f64 {
+        self.integrate(PI, |_| 1.0) / (4.0 * PI)
+    }
+
+    // Cosine-weighted mean over the nadir hemisphere, the factor by which
+    // the profile changes the power of a planar emitter.
+    pub fn cosine_average(&self) -> f64 {
+        self.integrate(PI / 2.0, |theta: f64| theta.cos()) / PI
+    }
+
+    fn integrate(&self, theta_max: f64, weight: impl Fn(f64) -> f64) -> f64 {
+        const STEPS: usize = 64;
+        let d_theta = theta_max / STEPS as f64;
+        let d_phi = 2.0 * PI / (2 * STEPS) as f64;
+        let mut sum = 0.0;
+        for i in 0..STEPS {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..2 * STEPS {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let local = Vec3 {
+                    x: theta.sin() * phi.cos(),
+                    y: theta.sin() * phi.sin(),
+                    z: theta.cos(),
+                };
+                sum += self.multiplier(local) * weight(theta) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // quadrant-symmetric fixture, candela halved by the multiplier of 2
+    const QUADRANT: &str = "IESNA:LM-63-2002
+[TEST] quadrant
+TILT=NONE
+1 1000 2 3 3 1 1 0 0 0
+1 1 100
+0 45 90
+0 45 90
+100 80 0
+60 50 0
+20 10 0
+";
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn parses_angles_and_scaled_candela() {
+        let profile = IesProfile::parse(QUADRANT).unwrap();
+        assert_eq!(profile.vertical_angles, vec![0.0, 45.0, 90.0]);
+        assert_eq!(profile.horizontal_angles, vec![0.0, 45.0, 90.0]);
+        assert_eq!(profile.candela[1], vec![120.0, 100.0, 0.0]);
+        assert!(approx(profile.max_candela, 200.0));
+        assert!(approx(profile.candela(0.0, 0.0), 200.0));
+        assert!(approx(profile.candela(22.5, 0.0), 180.0));
+        assert!(approx(profile.candela(0.0, 22.5), 160.0));
+        // below the last vertical angle there is no light
+        assert!(approx(profile.candela(120.0, 0.0), 0.0));
+        assert!(approx(
+            profile.multiplier(Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0
+            }),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn folds_quadrant_symmetry() {
+        let profile = IesProfile::parse(QUADRANT).unwrap();
+        for &theta in &[10.0, 30.0, 60.0] {
+            let c = profile.candela(theta, 30.0);
+            for &phi in &[150.0, 210.0, 330.0, -30.0] {
+                assert!(approx(profile.candela(theta, phi), c));
+            }
+        }
+    }
+
+    #[test]
+    fn folds_bilateral_and_rotational_symmetry() {
+        let bilateral = "TILT=NONE
+1 1000 1 2 2 1 1 0 0 0
+1 1 100
+0 90
+0 180
+10 5
+30 15
+";
+        let profile = IesProfile::parse(bilateral).unwrap();
+        assert!(approx(
+            profile.candela(0.0, 270.0),
+            profile.candela(0.0, 90.0)
+        ));
+        assert!(approx(profile.candela(0.0, 90.0), 20.0));
+
+        let rotational = "TILT=NONE
+1 1000 1 2 1 1 1 0 0 0
+1 1 100
+0 90
+0
+10 5
+";
+        let profile = IesProfile::parse(rotational).unwrap();
+        for &phi in &[0.0, 73.0, 181.0, 359.0] {
+            assert!(approx(profile.candela(45.0, phi), 7.5));
+        }
+    }
+
+    #[test]
+    fn skips_tilt_data_and_rejects_other_photometry() {
+        let tilted = "TILT=INCLUDE
+1
+2
+0 90
+1 0.5
+1 1000 1 1 1 1 1 0 0 0
+1 1 100
+0
+0
+42
+";
+        assert!(approx(IesProfile::parse(tilted).unwrap().max_candela, 42.0));
+        let type_b = "TILT=NONE
+1 1000 1 1 1 2 1 0 0 0
+1 1 100
+0
+0
+42
+";
+        assert!(IesProfile::parse(type_b).is_none());
+    }
+}
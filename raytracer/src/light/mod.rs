@@ -0,0 +1,36 @@
+use crate::*;
+
+pub mod environment;
+pub mod groups;
+pub mod ies;
+pub mod portal;
+pub mod punctual;
+pub mod sky;
+pub use environment::*;
+pub use groups::*;
+pub use ies::*;
+pub use portal::*;
+pub use punctual::*;
+pub use sky::*;
+
+// Radius of a sphere around the scene, whose cross-section distant lights
+// send their power through.
+pub fn scene_radius(world: &HittableList) -> f64 {
+    world
+        .bounding_box(0.0, 1.0)
+        .map_or(0.0, |b| (b.maximum - b.minimum).length() / 2.0)
+}
+
+// What a ray sees when it leaves the scene.
+pub trait Background: Send + Sync {
+    fn value(&self, dir: Vec3) -> Color;
+    fn light_group(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Background for Color {
+    fn value(&self, _dir: Vec3) -> Color {
+        *self
+    }
+}
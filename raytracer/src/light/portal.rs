@@ -0,0 +1,191 @@
+use crate::*;
+
+// Axis-aligned opening through which the environment is visible, laid out
+// like the rectangles in hittable/aarect.rs.
+#[derive(Clone, Copy)]
+pub struct PortalRect {
+    pub corner: Point3,
+    pub ex: Vec3,
+    pub ey: Vec3,
+}
+
+impl PortalRect {
+    pub fn new_xy(x0: f64, x1: f64, y0: f64, y1: f64, k: f64) -> Self {
+        PortalRect {
+            corner: Point3 { x: x0, y: y0, z: k },
+            ex: Vec3 {
+                x: x1 - x0,
+                y: 0.0,
+                z: 0.0,
+            },
+            ey: Vec3 {
+                x: 0.0,
+                y: y1 - y0,
+                z: 0.0,
+            },
+        }
+    }
+    pub fn new_xz(x0: f64, x1: f64, z0: f64, z1: f64, k: f64) -> Self {
+        PortalRect {
+            corner: Point3 { x: x0, y: k, z: z0 },
+            ex: Vec3 {
+                x: x1 - x0,
+                y: 0.0,
+                z: 0.0,
+            },
+            ey: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: z1 - z0,
+            },
+        }
+    }
+    pub fn new_yz(y0: f64, y1: f64, z0: f64, z1: f64, k: f64) -> Self {
+        PortalRect {
+            corner: Point3 { x: k, y: y0, z: z0 },
+            ex: Vec3 {
+                x: 0.0,
+                y: y1 - y0,
+                z: 0.0,
+            },
+            ey: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: z1 - z0,
+            },
+        }
+    }
+
+    // Ray parameter where o + t * v crosses the opening, from either side.
+    fn hit_t(&self, o: Point3, v: Vec3) -> Option<f64> {
+        let normal = self.ex ^ self.ey;
+        let denom = dot(v, normal);
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let t = dot(self.corner - o, normal) / denom;
+        if t < 0.001 {
+            return None;
+        }
+        let d = o + v * t - self.corner;
+        let a = dot(d, self.ex) / self.ex.length_squared();
+        let b = dot(d, self.ey) / self.ey.length_squared();
+        if (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+// Samples the environment through a set of portals. Each portal is picked
+// in proportion to the solid angle it subtends, then sampled uniformly
+// inside it, so the pdf of a direction is the selection-weighted sum over
+// every portal it passes through. That ignores where the environment is
+// bright, so a share of the samples follows the environment's own
+// distribution instead, the two combined by their mixture pdf (the balance
+// heuristic). Use it in the light list in place of the environment light;
+// the environment stays the background.
+pub struct PortalLight {
+    pub portals: Vec<PortalRect>,
+    pub env: Arc<dyn DistantLight>,
+    // flux the environment sends through each portal
+    powers: Vec<f64>,
+}
+
+// share of directions sampled from the environment rather than the portals
+const ENV_SHARE: f64 = 0.5;
+
+// Lights at infinity that can also be looked up as a background.
+pub trait DistantLight: Hittable + Background {}
+
+impl<T: Hittable + Background> DistantLight for T {}
+
+impl PortalLight {
+    pub fn new(env: Arc<dyn DistantLight>) -> Self {
+        PortalLight {
+            portals: Vec::new(),
+            env,
+            powers: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, portal: PortalRect) {
+        self.powers.push(self.flux_through(&portal));
+        self.portals.push(portal);
+    }
+
+    // Area times the cosine-weighted irradiance of the environment, taken on
+    // whichever side of the opening is brighter since the outside of a
+    // window is not known. Integrated over a grid of equirect directions.
+    fn flux_through(&self, portal: &PortalRect) -> f64 {
+        const WIDTH: usize = 128;
+        const HEIGHT: usize = 64;
+        let normal = portal.ex ^ portal.ey;
+        let area = normal.length();
+        let normal = normal / area;
+        let (mut front, mut back) = (0.0, 0.0);
+        for j in 0..HEIGHT {
+            let v = (j as f64 + 0.5) / HEIGHT as f64;
+            let d_omega = (PI * v).sin() * (PI / HEIGHT as f64) * (2.0 * PI / WIDTH as f64);
+            for i in 0..WIDTH {
+                let dir = equirect_to_direction((i as f64 + 0.5) / WIDTH as f64, v);
+                let l = luminance(self.env.value(dir)) * d_omega;
+                let cos = dot(dir, normal);
+                if cos > 0.0 {
+                    front += l * cos;
+                } else {
+                    back -= l * cos;
+                }
+            }
+        }
+        area * f64::max(front, back)
+    }
+
+    fn solid_angles(&self, o: Point3) -> Vec<f64> {
+        self.portals
+            .iter()
+            .map(|portal| {
+                SphericalRectangle::new(o, portal.corner, portal.ex, portal.ey).solid_angle
+            })
+            .collect()
+    }
+}
+
+impl Hittable for PortalLight {
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        let weights = self.solid_angles(o);
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return self.env.pdf_value(o, v);
+        }
+        let portal_pdf: f64 = self
+            .portals
+            .iter()
+            .zip(weights.iter())
+            .filter_map(|(portal, weight)| {
+                let t = portal.hit_t(o, v)?;
+                Some(weight / total * rect_pdf_value(o, portal.corner, portal.ex, portal.ey, v, t))
+            })
+            .sum();
+        (1.0 - ENV_SHARE) * portal_pdf + ENV_SHARE * self.env.pdf_value(o, v)
+    }
+    fn random(&self, o: Vec3) -> Vec3 {
+        let weights = self.solid_angles(o);
+        if weights.iter().sum::<f64>() <= 0.0 || random_double_default() < ENV_SHARE {
+            return self.env.random(o);
+        }
+        let (index, _) = Distribution1D::new(&weights).sample_discrete(random_double_default());
+        let portal = &self.portals[index];
+        rect_random(o, portal.corner, portal.ex, portal.ey)
+    }
+    fn power(&self) -> f64 {
+        self.powers.iter().sum()
+    }
+}
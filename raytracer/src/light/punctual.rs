@@ -0,0 +1,124 @@
+use crate::*;
+
+// Lights without area. They cannot be hit or sampled through a pdf, so
+// ray_color connects to each of them with a shadow ray.
+pub trait PunctualLight: Send + Sync {
+    // unit direction and distance from p to the light, and the intensity
+    // it sends towards p before the inverse square falloff
+    fn sample_li(&self, p: Point3) -> (Vec3, f64, Color);
+    fn light_group(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+    profile: Option<(Arc<IesProfile>, ONB)>,
+    pub group: Option<String>,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+            profile: None,
+            group: None,
+        }
+    }
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+    // the profile's nadir points along `nadir` and its C=0 plane through
+    // `tangent`
+    pub fn with_profile(mut self, profile: Arc<IesProfile>, nadir: Vec3, tangent: Vec3) -> Self {
+        self.profile = Some((profile, ONB::build_from_wu(nadir, tangent)));
+        self
+    }
+}
+
+impl PunctualLight for PointLight {
+    fn sample_li(&self, p: Point3) -> (Vec3, f64, Color) {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        let dir = to_light / distance;
+        let intensity = match &self.profile {
+            Some((profile, frame)) => self.intensity * profile.multiplier_in(frame, -dir),
+            None => self.intensity,
+        };
+        (dir, distance, intensity)
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// Cone light with a smooth falloff between the inner and outer angles.
+// An IES profile, if any, is aimed along the spot direction with its C=0
+// plane through the tangent given to with_profile.
+pub struct SpotLight {
+    pub position: Point3,
+    pub intensity: Color,
+    frame: ONB,
+    cos_inner: f64,
+    cos_outer: f64,
+    profile: Option<Arc<IesProfile>>,
+    pub group: Option<String>,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> Self {
+        SpotLight {
+            position,
+            intensity,
+            frame: ONB::build_from_w(direction),
+            cos_inner: degrees_to_radians(inner_angle).cos(),
+            cos_outer: degrees_to_radians(outer_angle).cos(),
+            profile: None,
+            group: None,
+        }
+    }
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+    pub fn with_profile(mut self, profile: Arc<IesProfile>, tangent: Vec3) -> Self {
+        self.frame = ONB::build_from_wu(self.frame.w(), tangent);
+        self.profile = Some(profile);
+        self
+    }
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_inner {
+            1.0
+        } else if cos_theta <= self.cos_outer {
+            0.0
+        } else {
+            let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl PunctualLight for SpotLight {
+    fn sample_li(&self, p: Point3) -> (Vec3, f64, Color) {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        let dir = to_light / distance;
+        let mut scale = self.falloff(dot(-dir, self.frame.w()));
+        if let Some(profile) = &self.profile {
+            scale *= profile.multiplier_in(&self.frame, -dir);
+        }
+        (dir, distance, self.intensity * scale)
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
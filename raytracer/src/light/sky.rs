@@ -0,0 +1,289 @@
+use crate::*;
+
+const SKY_TABLE_WIDTH: usize = 256;
+const SKY_TABLE_HEIGHT: usize = 128;
+// angular radius of the sun seen from the earth
+const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+fn direction_from_angles(elevation: f64, azimuth: f64) -> Vec3 {
+    let elevation = degrees_to_radians(elevation);
+    let azimuth = degrees_to_radians(azimuth);
+    Vec3 {
+        x: elevation.cos() * azimuth.cos(),
+        y: elevation.sin(),
+        z: elevation.cos() * azimuth.sin(),
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    fn f(&self, cos_theta: f64, gamma: f64) -> f64 {
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos() * gamma.cos())
+    }
+}
+
+// Disk light standing in for the sun, sampled uniformly inside its cone.
+// Like the sky, its power is what crosses a sphere of `scene_radius`.
+#[derive(Clone, Copy)]
+pub struct SunDisk {
+    pub direction: Vec3,
+    pub cos_theta_max: f64,
+    pub radiance: Color,
+    pub scene_radius: f64,
+}
+
+impl SunDisk {
+    pub fn new(direction: Vec3, angular_radius: f64, radiance: Color) -> Self {
+        SunDisk {
+            direction: unit_vector(direction),
+            cos_theta_max: angular_radius.cos(),
+            radiance,
+            scene_radius: 0.0,
+        }
+    }
+
+    pub fn contains(&self, dir: Vec3) -> bool {
+        dot(unit_vector(dir), self.direction) >= self.cos_theta_max
+    }
+
+    pub fn solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_theta_max)
+    }
+}
+
+impl Background for SunDisk {
+    fn value(&self, dir: Vec3) -> Color {
+        if self.contains(dir) {
+            self.radiance
+        } else {
+            Color::new()
+        }
+    }
+}
+
+impl Hittable for SunDisk {
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+    fn pdf_value(&self, _o: Point3, v: Vec3) -> f64 {
+        if self.contains(v) {
+            1.0 / self.solid_angle()
+        } else {
+            0.0
+        }
+    }
+    fn random(&self, _o: Vec3) -> Vec3 {
+        let sin_theta_max = (1.0 - self.cos_theta_max * self.cos_theta_max).sqrt();
+        ONB::build_from_w(self.direction).local_vec(random_to_sphere(sin_theta_max, 1.0))
+    }
+    fn power(&self) -> f64 {
+        let area = PI * self.scene_radius * self.scene_radius;
+        area * luminance(self.radiance) * self.solid_angle()
+    }
+}
+
+// Preetham et al. 1999 analytic daylight. Sky luminance is expressed
+// relative to the zenith and scaled by `intensity`; the sun is a separate
+// disk light whose colour comes from the same turbidity.
+pub struct PhysicalSky {
+    pub turbidity: f64,
+    pub intensity: f64,
+    pub sun: SunDisk,
+    theta_s: f64,
+    zenith_luminance: f64,
+    zenith_x: f64,
+    zenith_y: f64,
+    perez: [Perez; 3],
+    distribution: Distribution2D,
+    pub scene_radius: f64,
+    pub group: Option<String>,
+}
+
+impl PhysicalSky {
+    pub fn new(
+        turbidity: f64,
+        sun_elevation: f64,
+        sun_azimuth: f64,
+        intensity: f64,
+        sun_intensity: f64,
+    ) -> Self {
+        let t = turbidity;
+        let sun_direction = direction_from_angles(sun_elevation, sun_azimuth);
+        let theta_s = clamp(sun_direction.y, -1.0, 1.0)
+            .acos()
+            .min(PI / 2.0 - 0.001);
+        let (t2, s, s2, s3) = (
+            t * t,
+            theta_s,
+            theta_s * theta_s,
+            theta_s * theta_s * theta_s,
+        );
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_y_chroma = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        let sun = SunDisk::new(
+            sun_direction,
+            SUN_ANGULAR_RADIUS,
+            Self::sun_transmittance(t, theta_s) * sun_intensity,
+        );
+
+        let mut sky = PhysicalSky {
+            turbidity,
+            intensity,
+            sun,
+            theta_s,
+            zenith_luminance: zenith_y.max(0.0),
+            zenith_x,
+            zenith_y: zenith_y_chroma,
+            perez,
+            distribution: Distribution2D::new(&[1.0], 1, 1),
+            scene_radius: 0.0,
+            group: None,
+        };
+        sky.distribution = equirect_distribution(SKY_TABLE_WIDTH, SKY_TABLE_HEIGHT, |i, j| {
+            let dir = equirect_to_direction(
+                (i as f64 + 0.5) / SKY_TABLE_WIDTH as f64,
+                (j as f64 + 0.5) / SKY_TABLE_HEIGHT as f64,
+            );
+            luminance(sky.sky_radiance(dir))
+        });
+        sky
+    }
+
+    // Rayleigh and aerosol extinction along the sun's path through the
+    // atmosphere, evaluated at one wavelength per channel.
+    fn sun_transmittance(turbidity: f64, theta_s: f64) -> Color {
+        let beta = 0.04608 * turbidity - 0.04586;
+        let relative_mass =
+            1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let channel = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * relative_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * relative_mass).exp();
+            rayleigh * aerosol
+        };
+        Color {
+            x: channel(0.680),
+            y: channel(0.550),
+            z: channel(0.440),
+        }
+    }
+
+    pub fn sky_radiance(&self, dir: Vec3) -> Color {
+        let dir = unit_vector(dir);
+        // the model is only defined above the horizon
+        if dir.y < 0.0 {
+            return Color::new();
+        }
+        let cos_theta = dir.y.max(0.001);
+        let gamma = clamp(dot(dir, self.sun.direction), -1.0, 1.0).acos();
+        let ratio =
+            |k: usize| self.perez[k].f(cos_theta, gamma) / self.perez[k].f(1.0, self.theta_s);
+        let big_y = self.intensity * ratio(0);
+        let x = self.zenith_x * ratio(1);
+        let y = self.zenith_y * ratio(2);
+        if y <= 0.0 {
+            return Color::new();
+        }
+        let xyz = Vec3 {
+            x: x / y * big_y,
+            y: big_y,
+            z: (1.0 - x - y) / y * big_y,
+        };
+        let rgb = xyz_to_linear_srgb(xyz);
+        Color {
+            x: rgb.x.max(0.0),
+            y: rgb.y.max(0.0),
+            z: rgb.z.max(0.0),
+        }
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    // sets the size of the scene for both the sky and its sun
+    pub fn with_scene_radius(mut self, radius: f64) -> Self {
+        self.scene_radius = radius;
+        self.sun.scene_radius = radius;
+        self
+    }
+
+    // absolute zenith luminance of the model in kcd/m^2
+    pub fn zenith_luminance(&self) -> f64 {
+        self.zenith_luminance
+    }
+}
+
+impl Background for PhysicalSky {
+    fn value(&self, dir: Vec3) -> Color {
+        self.sky_radiance(dir) + self.sun.value(dir)
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+impl Hittable for PhysicalSky {
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+    fn pdf_value(&self, _o: Point3, v: Vec3) -> f64 {
+        equirect_pdf(&self.distribution, v)
+    }
+    fn random(&self, _o: Vec3) -> Vec3 {
+        let ((u, v), _) = self
+            .distribution
+            .sample_continuous(random_double_default(), random_double_default());
+        equirect_to_direction(u, v)
+    }
+    fn power(&self) -> f64 {
+        PI * self.scene_radius * self.scene_radius * equirect_integral(&self.distribution)
+    }
+}
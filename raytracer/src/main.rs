@@ -2,6 +2,7 @@
 
 mod camera;
 mod hittable;
+mod light;
 mod material;
 mod pdf;
 mod scene;
@@ -16,6 +17,7 @@ use std::{
 
 use camera::*;
 use hittable::*;
+use light::*;
 use material::*;
 use pdf::*;
 use scene::*;
@@ -25,46 +27,186 @@ use utility::*;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::time::Duration;
 
-fn ray_color<'a, H: Hittable>(
-    r: &Ray,
-    background: Color,
+// Everything a path needs besides the ray itself.
+struct RenderContext<'a, H: Hittable> {
+    background: &'a dyn Background,
     world: &'a HittableList,
     lights: &'a H,
+    punctual_lights: &'a [Arc<dyn PunctualLight>],
+    light_groups: &'a LightGroups,
+    fog: Option<&'a Fog>,
+    key_light: KeyLight,
+}
+
+// Where toon shading places the key light when there is no punctual light.
+// It is fixed for the whole render so the bands do not dither.
+#[derive(Clone, Copy)]
+enum KeyLight {
+    // centroid of the brightest emitter
+    At(Point3),
+    // mean direction of the brightest light at infinity
+    Towards(Vec3),
+    // the viewer, when nothing emits
+    Headlight,
+}
+
+impl KeyLight {
+    fn of(lights: &LightList) -> Self {
+        let brightest = lights
+            .powers
+            .iter()
+            .enumerate()
+            .filter(|(_, &power)| power > 0.0)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        let light = match brightest {
+            Some((index, _)) => &lights.objects[index],
+            None => return KeyLight::Headlight,
+        };
+        match light.bounding_box(0.0, 1.0) {
+            Some(bbox) => KeyLight::At((bbox.minimum + bbox.maximum) * 0.5),
+            None => {
+                let mut sum = Vec3::new();
+                for _ in 0..256 {
+                    sum += unit_vector(light.random(Point3::new()));
+                }
+                KeyLight::Towards(unit_vector(sum))
+            }
+        }
+    }
+}
+
+// Direction from rec towards the key light of the scene, None when it is
+// blocked: the first punctual light, else ctx.key_light.
+fn key_light<H: Hittable>(r: &Ray, rec: &HitRecord, ctx: &RenderContext<H>) -> Option<Vec3> {
+    let (dir, distance) = if let Some(light) = ctx.punctual_lights.first() {
+        let (dir, distance, _) = light.sample_li(rec.p);
+        (dir, distance)
+    } else {
+        match ctx.key_light {
+            KeyLight::At(p) => {
+                let dir = unit_vector(p - rec.p);
+                // the centroid may lie inside the emitter, so stop at its surface
+                let distance = ctx
+                    .lights
+                    .hit(&r.scattered(rec.p, dir), 0.001, INFINITY)
+                    .map_or((p - rec.p).length(), |light| light.t);
+                (dir, distance)
+            }
+            KeyLight::Towards(dir) => (dir, INFINITY),
+            KeyLight::Headlight => return Some(-unit_vector(r.dir)),
+        }
+    };
+    let shadow_ray = r.scattered(rec.p, dir);
+    let mut visibility = ctx
+        .world
+        .transmittance(&shadow_ray, 0.001, distance - 0.001);
+    if let Some(fog) = ctx.fog {
+        visibility *= fog.transmittance(&shadow_ray, 0.001, distance - 0.001);
+    }
+    if visibility > 0.0 {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+// Adds the radiance along r, times the throughput `weight` of the path so
+// far, to the light groups in `result`.
+fn ray_color<H: Hittable>(
+    r: &Ray,
+    ctx: &RenderContext<H>,
     depth: i32,
-) -> Vec3 {
+    weight: Color,
+    result: &mut [Color],
+) {
     if depth <= 0 {
-        return Vec3::new();
+        return;
+    }
+
+    let mut hit = ctx.world.hit(r, 0.001, INFINITY);
+    // the global fog may scatter the ray before it reaches anything
+    if let Some(fog) = ctx.fog {
+        let t_max = hit.as_ref().map_or(INFINITY, |rec| rec.t);
+        if let Some(rec) = fog.hit(r, 0.001, t_max) {
+            hit = Some(rec);
+        }
     }
 
-    match world.hit(r, 0.001, INFINITY) {
+    // absorbed on the way by the nested dielectric the ray travels through,
+    // so nothing gets out of an absorbing one without hitting its boundary
+    let weight = match r.media.top() {
+        Some(medium) => {
+            let distance = hit.as_ref().map_or(INFINITY, |rec| rec.t * r.dir.length());
+            weight * color_at_wavelength(medium.transmittance(distance), r.lambda)
+        }
+        None => weight,
+    };
+
+    match hit {
         Some(rec) => {
-            let emitted = rec.mat_ptr.emitted(r, &rec, rec.u, rec.v, rec.p);
+            // in spectral mode every colour is reduced to the path's wavelength
+            let spectral = |c: Color| color_at_wavelength(c, r.lambda);
+            let group = ctx.light_groups.index(rec.mat_ptr.light_group());
+            if let Some(shade) = rec.mat_ptr.cel_shade(r, &rec, &|| key_light(r, &rec, ctx)) {
+                result[group] += weight * spectral(shade);
+                return;
+            }
+            let emitted = spectral(rec.mat_ptr.emitted(r, &rec, rec.u, rec.v, rec.p));
+            result[group] += weight * emitted;
             //let mut pdf: f64 = 0.0;
 
-            match (*rec.mat_ptr).scatter(r, &rec) {
-                None => emitted,
-                Some(srec) => {
-                    if srec.is_specular {
-                        return srec.attenuation
-                            * ray_color(&srec.specular_ray, background, world, lights, depth - 1);
+            if let Some(srec) = (*rec.mat_ptr).scatter(r, &rec) {
+                if srec.is_specular {
+                    let attenuation = spectral(srec.attenuation);
+                    ray_color(
+                        &srec.specular_ray,
+                        ctx,
+                        depth - 1,
+                        weight * attenuation,
+                        result,
+                    );
+                    return;
+                }
+
+                // lights without area are only reachable through shadow rays
+                for light in ctx.punctual_lights {
+                    let (dir, distance, intensity) = light.sample_li(rec.p);
+                    let shadow_ray = r.scattered(rec.p, dir);
+                    if intensity.near_zero() {
+                        continue;
+                    }
+                    let mut visibility =
+                        ctx.world
+                            .transmittance(&shadow_ray, 0.001, distance - 0.001);
+                    if let Some(fog) = ctx.fog {
+                        visibility *= fog.transmittance(&shadow_ray, 0.001, distance - 0.001);
+                    }
+                    if visibility <= 0.0 {
+                        continue;
                     }
+                    result[ctx.light_groups.index(light.light_group())] += weight
+                        * spectral(rec.mat_ptr.scattering_value(r, &rec, &srec, &shadow_ray))
+                        * spectral(intensity)
+                        * visibility
+                        / (distance * distance);
+                }
 
-                    let light_ptr = HittablePdf::new(lights, rec.p);
+                let light_ptr = HittablePdf::new(ctx.lights, rec.p);
 
-                    let p = MixturePdf::new(srec.pdf_ptr.as_ref(), &light_ptr);
-                    //let p = srec.pdf_ptr.as_ref();
-                    let scattered = Ray::new_tm(rec.p, p.generate(), r.tm);
-                    let pdf_val = p.value(scattered.dir);
+                let p = MixturePdf::new(srec.pdf_ptr.as_ref(), &light_ptr);
+                //let p = srec.pdf_ptr.as_ref();
+                let scattered = r.scattered(rec.p, p.generate());
+                let pdf_val = p.value(scattered.dir);
 
-                    emitted
-                        + srec.attenuation
-                            * ray_color(&scattered, background, world, lights, depth - 1)
-                            * rec.mat_ptr.scattering_pdf(r, &rec, &scattered)
-                            / pdf_val
-                }
+                let throughput =
+                    spectral(rec.mat_ptr.scattering_value(r, &rec, &srec, &scattered)) / pdf_val;
+                ray_color(&scattered, ctx, depth - 1, weight * throughput, result);
             }
         }
-        None => background,
+        None => {
+            result[ctx.light_groups.index(ctx.background.light_group())] +=
+                weight * color_at_wavelength(ctx.background.value(r.dir), r.lambda);
+        }
     }
 }
 
@@ -96,37 +238,67 @@ fn main() {
     let mut lookat = Point3::new();
     let mut vfov = 40.0;
     let mut aperture = 0.0;
-    let mut background = Color::new();
+    let mut background: Arc<dyn Background> = Arc::new(Color::new());
 
-    let mut lights_list = HittableList::new();
+    let mut lights_list = LightList::new();
     lights_list.add(Arc::new(XZRect::new(
         123.0,
         423.0,
         147.0,
         412.0,
         554.0,
-        Lambertian::new(Vec3::new()),
+        DiffuseLight::new(Color {
+            x: 7.0,
+            y: 7.0,
+            z: 7.0,
+        }),
     )));
-    // lights_list.add(Arc::new(Sphere {
-    //     center: Vec3 {
+    let mut punctual_lights: Vec<Arc<dyn PunctualLight>> = Vec::new();
+    // emitters tagged with one of these names are also written to their own
+    // float buffer, e.g. DiffuseLight::new(c).with_group("key")
+    let light_groups = LightGroups::new(&[]);
+    // trace one wavelength per path so dispersive dielectrics split light
+    let spectral = false;
+    let spectral_sampler = SpectralSampler::new();
+    // haze over the whole scene without boundary geometry, e.g.
+    // Some(Arc::new(Fog::new_height(0.002, Color { .. }, 0.6, 0.0, 0.01, 1000.0)))
+    let mut fog: Option<Arc<Fog>> = None;
+    // non-photorealistic mode: lines along depth, normal and object edges
+    // are drawn over the render, see arknights_toon
+    let mut outline: Option<Outline> = None;
+    // punctual_lights.push(Arc::new(
+    //     SpotLight::new(position, direction, intensity, 20.0, 30.0)
+    //         .with_profile(Arc::new(IesProfile::new("input/fixture.ies")), tangent),
+    // ));
+    // distant lights need the size of the scene to weigh against the others,
+    // e.g. scene_radius(&world) once the world is built
+    // let env = Arc::new(
+    //     EnvironmentLight::new("input/sky.hdr", 1.0, 0.0).with_scene_radius(500.0),
+    // );
+    // lights_list.add(env.clone());
+    // for interiors, sample the environment through the windows instead:
+    // let mut portals = PortalLight::new(env.clone());
+    // portals.add(PortalRect::new_yz(100.0, 300.0, 150.0, 400.0, 555.0));
+    // lights_list.add(Arc::new(portals));
+    // lights_list.add(Arc::new(Sphere::new(
+    //     Vec3 {
     //         x: 278.0,
     //         y: 25.0,
     //         z: 100.0,
     //     },
-    //     radius: 25.0,
-    //     mat_ptr: Lambertian::new(Vec3::new()),
-    // }));
-    let lights = lights_list;
+    //     25.0,
+    //     Lambertian::new(Vec3::new()),
+    // )));
 
     let world_type = 6;
     match world_type {
         1 => {
             world = random_scene();
-            background = Color {
+            background = Arc::new(Color {
                 x: 0.7,
                 y: 0.8,
                 z: 1.0,
-            };
+            });
             lookfrom = Point3 {
                 x: 13.0,
                 y: 2.0,
@@ -142,11 +314,11 @@ fn main() {
         }
         2 => {
             world = two_spheres();
-            background = Color {
+            background = Arc::new(Color {
                 x: 0.7,
                 y: 0.8,
                 z: 1.0,
-            };
+            });
             lookfrom = Point3 {
                 x: 13.0,
                 y: 2.0,
@@ -161,11 +333,11 @@ fn main() {
         }
         3 => {
             world = two_perlin_spheres();
-            background = Color {
+            background = Arc::new(Color {
                 x: 0.7,
                 y: 0.8,
                 z: 1.0,
-            };
+            });
             lookfrom = Point3 {
                 x: 13.0,
                 y: 2.0,
@@ -180,11 +352,11 @@ fn main() {
         }
         4 => {
             world = earth();
-            background = Color {
+            background = Arc::new(Color {
                 x: 0.7,
                 y: 0.8,
                 z: 1.0,
-            };
+            });
             lookfrom = Point3 {
                 x: 13.0,
                 y: 2.0,
@@ -199,13 +371,13 @@ fn main() {
         }
         5 => {
             world = simple_light();
-            background = Color::new();
+            background = Arc::new(Color::new());
             samples_per_pixel = 400;
-            background = Color {
+            background = Arc::new(Color {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
-            };
+            });
             lookfrom = Point3 {
                 x: 26.0,
                 y: 3.0,
@@ -220,14 +392,25 @@ fn main() {
         }
         6 => {
             world = arknights();
+            fog = Some(Arc::new(Fog::new(
+                0.0001,
+                Color {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                },
+                0.0,
+                5000.0,
+            )));
             aspect_ratio = 1.0;
             image_width = 800;
             samples_per_pixel = 10000;
-            // background = Color {
+            // background = Arc::new(Color {
             //     x: 0.7,
             //     y: 0.8,
             //     z: 0.9,
-            // };
+            // });
+            // background = env;
 
             lookfrom = Point3 {
                 x: 478.0,
@@ -246,11 +429,14 @@ fn main() {
             aspect_ratio = 1.0;
             image_width = 600;
             samples_per_pixel = 100;
-            background = Color {
-                x: 0.7,
-                y: 0.8,
-                z: 0.9,
-            };
+            let sky = Arc::new(
+                PhysicalSky::new(3.0, 35.0, 60.0, 1.0, 20000.0)
+                    .with_scene_radius(scene_radius(&world)),
+            );
+            lights_list = LightList::new();
+            lights_list.add(sky.clone());
+            lights_list.add(Arc::new(sky.sun));
+            background = sky;
             lookfrom = Point3 {
                 x: -100.0,
                 y: 65.0,
@@ -263,12 +449,53 @@ fn main() {
             };
             vfov = 40.0;
         }
+        8 => {
+            world = arknights_toon();
+            // the key light the toon shading and its shadows follow
+            punctual_lights.push(Arc::new(PointLight::new(
+                Point3 {
+                    x: 1300.0,
+                    y: 2000.0,
+                    z: -1000.0,
+                },
+                Color {
+                    x: 1e7,
+                    y: 1e7,
+                    z: 1e7,
+                },
+            )));
+            outline = Some(Outline::new(Color {
+                x: 0.05,
+                y: 0.05,
+                z: 0.08,
+            }));
+            aspect_ratio = 1.0;
+            image_width = 800;
+            // toon surfaces end every path, so few samples are needed
+            samples_per_pixel = 16;
+            background = Arc::new(Color {
+                x: 0.7,
+                y: 0.8,
+                z: 0.9,
+            });
+            lookfrom = Point3 {
+                x: 478.0,
+                y: 278.0,
+                z: -600.0,
+            };
+            lookat = Point3 {
+                x: 278.0,
+                y: 278.0,
+                z: 0.0,
+            };
+            vfov = 40.0;
+        }
         _ => {
             world = final_scene();
             aspect_ratio = 1.0;
             image_width = 800;
             samples_per_pixel = 10000;
-            background = Color::new();
+            background = Arc::new(Color::new());
             lookfrom = Point3 {
                 x: 478.0,
                 y: 278.0,
@@ -282,6 +509,15 @@ fn main() {
             vfov = 40.0;
         }
     }
+    // with many emitters, pick lights by their estimated contribution at
+    // each shading point rather than by power alone
+    const LIGHT_BVH_MIN_LIGHTS: usize = 16;
+    let key = KeyLight::of(&lights_list);
+    let lights: Arc<dyn Hittable> = if lights_list.objects.len() >= LIGHT_BVH_MIN_LIGHTS {
+        Arc::new(LightBvh::new(lights_list))
+    } else {
+        Arc::new(lights_list)
+    };
     let image_height: u32 = (image_width as f64 / aspect_ratio) as u32;
     //Camera
 
@@ -325,19 +561,49 @@ fn main() {
         receivers.push(rx);
         let world_tmp = world.clone();
         let lights_tmp = lights.clone();
+        let background_tmp = background.clone();
+        let punctual_tmp = punctual_lights.clone();
+        let light_groups_tmp = light_groups.clone();
+        let fog_tmp = fog.clone();
         let pb = multi_progress.add(ProgressBar::new(task.len() as u64 / PROGRESS_INC_NUM));
         let mut count = 0;
         let cur_thread: thread::JoinHandle<()> = thread::spawn(move || {
+            let ctx = RenderContext {
+                background: background_tmp.as_ref(),
+                world: &world_tmp,
+                lights: &lights_tmp,
+                punctual_lights: &punctual_tmp,
+                light_groups: &light_groups_tmp,
+                fog: fog_tmp.as_deref(),
+                key_light: key,
+            };
+            // radiance of one spectral sample, before it is turned into RGB
+            let mut sample_groups = ctx.light_groups.zero();
+            let one = Color {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            };
             for (i, j) in task {
-                let mut pixel_color = Vec3::new();
+                let mut pixel_groups = ctx.light_groups.zero();
 
                 for _s in 0..samples_per_pixel {
                     let u = (i as f64 + random_double_default()) / ((image_width - 1) as f64);
                     let v = (j as f64 + random_double_default()) / ((image_height - 1) as f64);
-                    let r = cam.get_ray(u, v, 0.0, 1.0);
-                    pixel_color += ray_color(&r, background, &world_tmp, &lights_tmp, max_depth);
+                    let mut r = cam.get_ray(u, v, 0.0, 1.0);
+                    if spectral {
+                        let (lambda, pdf) = spectral_sampler.sample(random_double_default());
+                        r.lambda = lambda;
+                        sample_groups.fill(Color::new());
+                        ray_color(&r, &ctx, max_depth, one, &mut sample_groups);
+                        for (sum, c) in pixel_groups.iter_mut().zip(sample_groups.iter()) {
+                            *sum += spectral_sampler.radiance_to_rgb(lambda, c.x, pdf);
+                        }
+                    } else {
+                        ray_color(&r, &ctx, max_depth, one, &mut pixel_groups);
+                    }
                 }
-                tx.send(ColorInformation::new(i, image_height - j - 1, pixel_color))
+                tx.send(ColorInformation::new(i, image_height - j - 1, pixel_groups))
                     .unwrap();
                 count += 1;
                 if count == PROGRESS_INC_NUM {
@@ -351,20 +617,45 @@ fn main() {
     }
     multi_progress.join_and_clear().unwrap();
 
+    let mut group_buffers =
+        vec![vec![Color::new(); (image_width * image_height) as usize]; light_groups.len()];
     for rx in receivers {
         for received in rx {
             let pixel = img.get_pixel_mut(received.i, received.j);
             *pixel = image::Rgb(write_color(received.color, samples_per_pixel));
+            let index = (received.j * image_width + received.i) as usize;
+            for (buffer, c) in group_buffers.iter_mut().zip(received.groups.iter()) {
+                buffer[index] = *c / samples_per_pixel as f64;
+            }
         }
     }
 
     for thread in threads {
         thread.join().unwrap();
     }
+    if let Some(outline) = outline {
+        outline.draw(&mut img, &mut group_buffers, &world, &cam);
+    }
     println!(
         "Ouput image as \"{}\"",
         style(path.to_str().unwrap()).yellow()
     );
+    if light_groups.len() > 1 {
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        for (name, buffer) in light_groups.names.iter().zip(group_buffers.iter()) {
+            let group_path = prefix.join(format!("{}_{}.hdr", stem, name));
+            write_hdr(
+                group_path.to_str().unwrap(),
+                buffer,
+                image_width as usize,
+                image_height as usize,
+            );
+            println!(
+                "Ouput light group as \"{}\"",
+                style(group_path.to_str().unwrap()).yellow()
+            );
+        }
+    }
     let output_image = image::DynamicImage::ImageRgb8(img);
     let mut output_file = File::create(path).unwrap();
     match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
@@ -0,0 +1,197 @@
+use crate::*;
+
+// A dielectric coat of index `ir` over any base material, in the spirit of
+// Weidlich and Wilkie 2007: light either reflects off the coat or passes it
+// twice, losing (1 - F) at each crossing and being absorbed along the
+// refracted path through the coat. The base is evaluated with the outside
+// directions. One lobe is picked per scatter in proportion to the coat's
+// Fresnel reflectance.
+#[derive(Clone)]
+pub struct Coated<M: Material> {
+    pub base: M,
+    pub ir: f64,
+    pub alpha: f64,
+    pub thickness: f64,
+    pub absorption: Color,
+}
+
+impl<M: Material> Coated<M> {
+    pub fn new(base: M, index_of_refraction: f64, roughness: f64) -> Self {
+        Coated {
+            base,
+            ir: index_of_refraction,
+            alpha: roughness_to_alpha(roughness),
+            thickness: 1.0,
+            absorption: Color::new(),
+        }
+    }
+    // `color` is the transmittance after travelling `distance` in the coat
+    pub fn with_absorption(mut self, color: Color, distance: f64) -> Self {
+        self.absorption = Dielectric::new_absorbing(self.ir, color, distance).absorption;
+        self
+    }
+    pub fn with_thickness(mut self, thickness: f64) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.alpha < MIN_ALPHA
+    }
+
+    fn fresnel(&self, cosine: f64) -> f64 {
+        fresnel_dielectric(cosine.abs(), self.ir)
+    }
+
+    // attenuation of light crossing the coat along wo and then wi
+    fn transmission(&self, wo: Vec3, wi: Vec3) -> Color {
+        let path = |w: Vec3| {
+            let sin2 = (1.0 - w.z * w.z).max(0.0) / (self.ir * self.ir);
+            1.0 / (1.0 - sin2).max(1e-4).sqrt()
+        };
+        let distance = self.thickness * (path(wo) + path(wi));
+        let crossing = (1.0 - self.fresnel(wo.z)) * (1.0 - self.fresnel(wi.z));
+        Color {
+            x: (-self.absorption.x * distance).exp(),
+            y: (-self.absorption.y * distance).exp(),
+            z: (-self.absorption.z * distance).exp(),
+        } * crossing
+    }
+
+    fn coat_value(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = unit_vector(wo + wi);
+        self.fresnel(dot(wo, h)) * ggx_d(h, self.alpha) * ggx_g(wo, wi, self.alpha) / (4.0 * wo.z)
+    }
+}
+
+impl<M: Material> Material for Coated<M> {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        let cosine = dot(-unit_vector(r_in.dir), rec.normal);
+        self.base.emitted(r_in, rec, u, v, p) * (1.0 - self.fresnel(cosine))
+    }
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0.0 {
+            return self.base.scatter(r_in, rec);
+        }
+        let f = self.fresnel(wo.z);
+        let base = self.base.scatter(r_in, rec);
+        let coat = if self.is_smooth() {
+            ScatterRecord {
+                specular_ray: r_in.scattered(rec.p, reflect(unit_vector(r_in.dir), rec.normal)),
+                is_specular: true,
+                attenuation: Color {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                },
+                pdf_ptr: Box::new(DefaultPdf {}),
+            }
+        } else {
+            ScatterRecord {
+                specular_ray: *r_in,
+                is_specular: false,
+                attenuation: Color::new(),
+                pdf_ptr: Box::new(GgxReflectionPdf::new(uvw, wo, self.alpha)),
+            }
+        };
+        let mut base = match base {
+            Some(base) => base,
+            None if random_double_default() < f => return Some(coat),
+            None => return None,
+        };
+        // A rough coat over a non-specular base is one lobe mixture; in
+        // every other case either the coat or the base is picked here with
+        // probability F and 1 - F, which scattering_value divides out.
+        if !self.is_smooth() && !base.is_specular {
+            let mut pdf = WeightedMixturePdf::new();
+            pdf.add(f, coat.pdf_ptr);
+            pdf.add(1.0 - f, base.pdf_ptr);
+            base.pdf_ptr = Box::new(pdf);
+            return Some(base);
+        }
+        if random_double_default() < f {
+            return Some(coat);
+        }
+        if base.is_specular {
+            let wi = uvw.world_to_local(unit_vector(base.specular_ray.dir));
+            base.attenuation = base.attenuation * self.transmission(wo, wi) / (1.0 - f);
+        }
+        Some(base)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0.0 {
+            return self.base.scattering_pdf(r_in, rec, scattered);
+        }
+        let coat = ggx_reflection_pdf(
+            wo,
+            uvw.world_to_local(unit_vector(scattered.dir)),
+            self.alpha,
+        );
+        let base = match self.base.scatter(r_in, rec) {
+            Some(base) if !base.is_specular => base.pdf_ptr.value(scattered.dir),
+            _ if self.is_smooth() => return 0.0,
+            _ => return coat,
+        };
+        if self.is_smooth() {
+            base
+        } else {
+            let f = self.fresnel(wo.z);
+            f * coat + (1.0 - f) * base
+        }
+    }
+    // Rebuilds the base's scatter record, so the base's attenuation must not
+    // depend on random numbers for non-specular scatters.
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0.0 {
+            return self.base.scattering_value(r_in, rec, srec, scattered);
+        }
+        let wi = uvw.world_to_local(unit_vector(scattered.dir));
+        let f = self.fresnel(wo.z);
+        let coat = self.coat_value(wo, wi);
+        let white = Color {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let base = match self.base.scatter(r_in, rec) {
+            Some(base) if !base.is_specular => base,
+            // only the rough coat is non-specular, picked with probability F
+            _ => return white * (coat / f),
+        };
+        let base_value =
+            self.base.scattering_value(r_in, rec, &base, scattered) * self.transmission(wo, wi);
+        if self.is_smooth() {
+            // the base was picked with probability 1 - F
+            base_value / (1.0 - f)
+        } else {
+            white * coat + base_value
+        }
+    }
+    fn emitted_power(&self, area: f64) -> f64 {
+        self.base.emitted_power(area)
+    }
+    fn resolve_area(&mut self, area: f64) {
+        self.base.resolve_area(area)
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.base.light_group()
+    }
+    fn emission_weight(&self, normal: Vec3, dir: Vec3) -> f64 {
+        self.base.emission_weight(normal, dir)
+    }
+}
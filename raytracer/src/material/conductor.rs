@@ -0,0 +1,115 @@
+use crate::*;
+
+// Rough metal with a GGX microfacet distribution and the Fresnel term of a
+// complex index of refraction, one value per RGB channel.
+#[derive(Clone)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub alpha: f64,
+    pub thin_film: Option<ThinFilm>,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Conductor {
+            eta,
+            k,
+            alpha: roughness_to_alpha(roughness),
+            thin_film: None,
+        }
+    }
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.thin_film = Some(film);
+        self
+    }
+
+    // indices sampled at 650, 550 and 450 nm
+    pub fn gold(roughness: f64) -> Self {
+        Self::preset([0.143, 0.374, 1.442], [3.983, 2.385, 1.603], roughness)
+    }
+    pub fn copper(roughness: f64) -> Self {
+        Self::preset([0.200, 0.924, 1.102], [3.912, 2.452, 2.142], roughness)
+    }
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::preset([1.657, 0.880, 0.521], [9.224, 6.270, 4.837], roughness)
+    }
+    pub fn silver(roughness: f64) -> Self {
+        Self::preset([0.155, 0.117, 0.138], [4.828, 3.122, 2.147], roughness)
+    }
+
+    fn preset(eta: [f64; 3], k: [f64; 3], roughness: f64) -> Self {
+        let color = |c: [f64; 3]| Color {
+            x: c[0],
+            y: c[1],
+            z: c[2],
+        };
+        Self::new(color(eta), color(k), roughness)
+    }
+
+    // Fresnel reflectance at rec, through the film if there is one
+    fn reflectance(&self, cos_i: f64, rec: &HitRecord, lambda: f64) -> Color {
+        match &self.thin_film {
+            Some(film) => film.reflectance(cos_i, 1.0, self.eta, self.k, rec, lambda),
+            None => self.fresnel(cos_i),
+        }
+    }
+
+    pub fn fresnel(&self, cos_i: f64) -> Color {
+        Color {
+            x: fresnel_conductor(cos_i, self.eta.x, self.k.x),
+            y: fresnel_conductor(cos_i, self.eta.y, self.k.y),
+            z: fresnel_conductor(cos_i, self.eta.z, self.k.z),
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0.0 {
+            return None;
+        }
+        if self.alpha < MIN_ALPHA {
+            let reflected = reflect(unit_vector(r_in.dir), rec.normal);
+            return Some(ScatterRecord {
+                specular_ray: r_in.scattered(rec.p, reflected),
+                is_specular: true,
+                attenuation: self.reflectance(wo.z, rec, r_in.lambda),
+                pdf_ptr: Box::new(DefaultPdf {}),
+            });
+        }
+        Some(ScatterRecord {
+            specular_ray: *r_in,
+            is_specular: false,
+            attenuation: self.reflectance(wo.z, rec, r_in.lambda),
+            pdf_ptr: Box::new(GgxReflectionPdf::new(uvw, wo, self.alpha)),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        let wi = uvw.world_to_local(unit_vector(scattered.dir));
+        ggx_reflection_pdf(wo, wi, self.alpha)
+    }
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        let wi = uvw.world_to_local(unit_vector(scattered.dir));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::new();
+        }
+        let h = unit_vector(wo + wi);
+        self.reflectance(dot(wo, h), rec, r_in.lambda)
+            * ggx_d(h, self.alpha)
+            * ggx_g(wo, wi, self.alpha)
+            / (4.0 * wo.z)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::*;
+
+// Phase function of a glowing medium. `emission` is the radiance emitted
+// per unit length (the absorption coefficient times the source radiance)
+// and `density` the extinction the medium is built with, so every real
+// collision adds emission / density, an unbiased estimate of the emission
+// gathered along the ray up to it.
+#[derive(Clone)]
+pub struct EmissiveVolume<M: Material> {
+    pub phase_function: M,
+    pub emission: Arc<dyn Texture>,
+    pub density: Arc<dyn Texture>,
+    pub group: Option<String>,
+}
+
+impl<M: Material> EmissiveVolume<M> {
+    pub fn new(phase_function: M, emission: Arc<dyn Texture>, density: Arc<dyn Texture>) -> Self {
+        EmissiveVolume {
+            phase_function,
+            emission,
+            density,
+            group: None,
+        }
+    }
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+}
+
+impl<M: Material> Material for EmissiveVolume<M> {
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        let density = self.density.value(u, v, p).x;
+        if density <= 0.0 {
+            return Color::new();
+        }
+        self.emission.value(u, v, p) / density
+    }
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        self.phase_function.scatter(r_in, rec)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.phase_function.scattering_pdf(r_in, rec, scattered)
+    }
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        self.phase_function
+            .scattering_value(r_in, rec, srec, scattered)
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
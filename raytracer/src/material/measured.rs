@@ -0,0 +1,270 @@
+use crate::*;
+
+// Resolution of the MERL isotropic BRDF database files (Matusik et al.
+// 2003), indexed by half angle, difference angle and difference azimuth.
+const MERL_THETA_H: usize = 90;
+const MERL_THETA_D: usize = 90;
+const MERL_PHI_D: usize = 180;
+const MERL_SIZE: usize = MERL_THETA_H * MERL_THETA_D * MERL_PHI_D;
+const MERL_SCALE: [f64; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+// resolution of the tables used for importance sampling
+const SAMPLE_THETA_O: usize = 32;
+const SAMPLE_THETA_I: usize = 32;
+const SAMPLE_PHI_I: usize = 64;
+// share of samples drawn from a cosine lobe, covering what the coarse tables
+// miss
+const COSINE_SHARE: f64 = 0.2;
+
+fn spherical(theta: f64, phi: f64) -> Vec3 {
+    Vec3 {
+        x: theta.sin() * phi.cos(),
+        y: theta.sin() * phi.sin(),
+        z: theta.cos(),
+    }
+}
+
+fn rotate_z(v: Vec3, angle: f64) -> Vec3 {
+    let (s, c) = angle.sin_cos();
+    Vec3 {
+        x: c * v.x - s * v.y,
+        y: s * v.x + c * v.y,
+        z: v.z,
+    }
+}
+
+fn rotate_y(v: Vec3, angle: f64) -> Vec3 {
+    let (s, c) = angle.sin_cos();
+    Vec3 {
+        x: c * v.x + s * v.z,
+        y: v.y,
+        z: -s * v.x + c * v.z,
+    }
+}
+
+// Measured isotropic BRDF in the MERL binary format: three i32 dimensions
+// followed by the red, green and blue tables as f64.
+pub struct MeasuredBrdf {
+    data: Vec<f64>,
+    // one table over (theta_i, phi_i - phi_o) per band of outgoing angles
+    tables: Vec<Arc<Distribution2D>>,
+}
+
+impl MeasuredBrdf {
+    pub fn new(filename: &str) -> Self {
+        let bytes = std::fs::read(filename).expect("Cannot open.");
+        let size = 12 + 8 * 3 * MERL_SIZE;
+        assert!(
+            bytes.len() >= 12,
+            "{} is too short for a MERL BRDF, expected {} bytes.",
+            filename,
+            size
+        );
+        let int = |i: usize| {
+            i32::from_le_bytes([
+                bytes[4 * i],
+                bytes[4 * i + 1],
+                bytes[4 * i + 2],
+                bytes[4 * i + 3],
+            ])
+        };
+        let dims = int(0) as usize * int(1) as usize * int(2) as usize;
+        assert!(
+            dims == MERL_SIZE && bytes.len() >= size,
+            "{} is not a {}x{}x{} MERL BRDF of {} bytes.",
+            filename,
+            MERL_THETA_H,
+            MERL_THETA_D,
+            MERL_PHI_D,
+            size
+        );
+        let data = bytes[12..size]
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .collect();
+        Self::from_data(data)
+    }
+
+    // `data` holds the three raw channel tables one after another
+    pub fn from_data(data: Vec<f64>) -> Self {
+        assert!(data.len() == 3 * MERL_SIZE);
+        let mut brdf = MeasuredBrdf {
+            data,
+            tables: Vec::new(),
+        };
+        brdf.tables = (0..SAMPLE_THETA_O)
+            .map(|o| Arc::new(brdf.build_table(o)))
+            .collect();
+        brdf
+    }
+
+    fn build_table(&self, band: usize) -> Distribution2D {
+        let theta_o = (band as f64 + 0.5) / SAMPLE_THETA_O as f64 * PI / 2.0;
+        let wo = spherical(theta_o, 0.0);
+        let mut func = Vec::with_capacity(SAMPLE_THETA_I * SAMPLE_PHI_I);
+        for i in 0..SAMPLE_THETA_I {
+            let theta_i = (i as f64 + 0.5) / SAMPLE_THETA_I as f64 * PI / 2.0;
+            for j in 0..SAMPLE_PHI_I {
+                let phi_i = (j as f64 + 0.5) / SAMPLE_PHI_I as f64 * 2.0 * PI;
+                let wi = spherical(theta_i, phi_i);
+                // the sine turns density over angles into solid angle
+                func.push(luminance(self.eval(wo, wi)) * wi.z * theta_i.sin());
+            }
+        }
+        Distribution2D::new(&func, SAMPLE_PHI_I, SAMPLE_THETA_I)
+    }
+
+    fn table(&self, wo: Vec3) -> Arc<Distribution2D> {
+        let theta_o = clamp(wo.z, -1.0, 1.0).acos();
+        let band = (theta_o / (PI / 2.0) * SAMPLE_THETA_O as f64) as usize;
+        self.tables[band.min(SAMPLE_THETA_O - 1)].clone()
+    }
+
+    // BRDF for directions in the shading frame
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::new();
+        }
+        let h = unit_vector(wo + wi);
+        let theta_h = clamp(h.z, -1.0, 1.0).acos();
+        let phi_h = h.y.atan2(h.x);
+        let d = rotate_y(rotate_z(wi, -phi_h), -theta_h);
+        let theta_d = clamp(d.z, -1.0, 1.0).acos();
+        let mut phi_d = d.y.atan2(d.x);
+        // reciprocity makes phi_d and phi_d + pi equal
+        if phi_d < 0.0 {
+            phi_d += PI;
+        }
+
+        let index_h = ((theta_h / (PI / 2.0)).max(0.0).sqrt() * MERL_THETA_H as f64) as usize;
+        let index_d = (theta_d / (PI / 2.0) * MERL_THETA_D as f64) as usize;
+        let index_phi = (phi_d / PI * MERL_PHI_D as f64) as usize;
+        let index = index_phi.min(MERL_PHI_D - 1)
+            + index_d.min(MERL_THETA_D - 1) * MERL_PHI_D
+            + index_h.min(MERL_THETA_H - 1) * MERL_PHI_D * MERL_THETA_D;
+        let channel = |c: usize| (self.data[index + c * MERL_SIZE] * MERL_SCALE[c]).max(0.0);
+        Color {
+            x: channel(0),
+            y: channel(1),
+            z: channel(2),
+        }
+    }
+
+    fn pdf(&self, uvw: ONB, wo: Vec3) -> WeightedMixturePdf {
+        let mut pdf = WeightedMixturePdf::new();
+        pdf.add(
+            1.0 - COSINE_SHARE,
+            Box::new(TabulatedBrdfPdf {
+                uvw,
+                phi_o: wo.y.atan2(wo.x),
+                table: self.table(wo),
+            }),
+        );
+        pdf.add(COSINE_SHARE, Box::new(CosinePdf::new(uvw.w())));
+        pdf
+    }
+}
+
+// Samples (theta_i, phi_i - phi_o) from a table built for the band of wo.
+pub struct TabulatedBrdfPdf {
+    pub uvw: ONB,
+    pub phi_o: f64,
+    pub table: Arc<Distribution2D>,
+}
+
+impl Pdf for TabulatedBrdfPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let wi = self.uvw.world_to_local(unit_vector(direction));
+        let sin_theta = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+        if wi.z <= 0.0 || sin_theta < 1e-6 {
+            return 0.0;
+        }
+        let theta = wi.z.acos();
+        let phi = (wi.y.atan2(wi.x) - self.phi_o).rem_euclid(2.0 * PI);
+        self.table.pdf(phi / (2.0 * PI), theta / (PI / 2.0)) / (PI * PI * sin_theta)
+    }
+    fn generate(&self) -> Vec3 {
+        let ((u, v), _) = self
+            .table
+            .sample_continuous(random_double_default(), random_double_default());
+        let wi = spherical(v * PI / 2.0, self.phi_o + u * 2.0 * PI);
+        self.uvw.local_vec(wi)
+    }
+}
+
+impl Material for MeasuredBrdf {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            specular_ray: *r_in,
+            is_specular: false,
+            attenuation: Color::new(),
+            pdf_ptr: Box::new(self.pdf(uvw, wo)),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        self.pdf(uvw, wo).value(scattered.dir)
+    }
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        let wi = uvw.world_to_local(unit_vector(scattered.dir));
+        self.eval(wo, wi) * wi.z.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // directions whose half and difference angles are (theta_h, theta_d,
+    // phi_d), with the half vector at azimuth phi_h
+    fn directions(theta_h: f64, theta_d: f64, phi_d: f64, phi_h: f64) -> (Vec3, Vec3) {
+        let h = spherical(theta_h, phi_h);
+        let wi = rotate_z(rotate_y(spherical(theta_d, phi_d), theta_h), phi_h);
+        (h * (2.0 * dot(wi, h)) - wi, wi)
+    }
+
+    #[test]
+    fn looks_up_the_measured_cell() {
+        // the half angle is stored on a square-root scale
+        let (index_h, index_d, index_phi) = (40, 20, 100);
+        let theta_h = ((index_h as f64 + 0.5) / MERL_THETA_H as f64).powi(2) * PI / 2.0;
+        let theta_d = (index_d as f64 + 0.5) / MERL_THETA_D as f64 * PI / 2.0;
+        let phi_d = (index_phi as f64 + 0.5) / MERL_PHI_D as f64 * PI;
+        let mut data = vec![0.0; 3 * MERL_SIZE];
+        let index = index_phi + (index_d + index_h * MERL_THETA_D) * MERL_PHI_D;
+        for (c, scale) in MERL_SCALE.iter().enumerate() {
+            data[index + c * MERL_SIZE] = 1.0 / scale;
+        }
+        let brdf = MeasuredBrdf::from_data(data);
+
+        let is_cell = |(wo, wi): (Vec3, Vec3)| {
+            let f = brdf.eval(wo, wi);
+            (f.x - 1.0).abs() < 1e-9 && (f.y - 1.0).abs() < 1e-9 && (f.z - 1.0).abs() < 1e-9
+        };
+        // isotropy, reciprocity and the phi_d + pi symmetry all reach it
+        for &phi_h in &[0.0, 1.0, 4.0] {
+            assert!(is_cell(directions(theta_h, theta_d, phi_d, phi_h)));
+            assert!(is_cell(directions(theta_h, theta_d, phi_d + PI, phi_h)));
+            let (wo, wi) = directions(theta_h, theta_d, phi_d, phi_h);
+            assert!(is_cell((wi, wo)));
+        }
+        // the neighbouring cells are empty
+        assert!(!is_cell(directions(theta_h, theta_d + 0.02, phi_d, 0.0)));
+        assert!(!is_cell(directions(theta_h, theta_d, phi_d + 0.02, 0.0)));
+        assert!(!is_cell(directions(theta_h * 1.1, theta_d, phi_d, 0.0)));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::*;
+
+// Trowbridge-Reitz (GGX) microfacet model. All vectors are in the shading
+// frame with z along the normal; alpha is the squared perceptual roughness.
+
+// below this alpha a surface is treated as a perfect mirror
+pub const MIN_ALPHA: f64 = 1e-3;
+
+pub fn roughness_to_alpha(roughness: f64) -> f64 {
+    let r = clamp(roughness, 0.0, 1.0);
+    r * r
+}
+
+pub fn ggx_d(h: Vec3, alpha: f64) -> f64 {
+    if h.z <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = h.z * h.z * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+pub fn ggx_lambda(w: Vec3, alpha: f64) -> f64 {
+    let cos2 = w.z * w.z;
+    if cos2 >= 1.0 {
+        return 0.0;
+    }
+    let tan2 = (1.0 - cos2) / cos2.max(1e-12);
+    0.5 * (-1.0 + (1.0 + alpha * alpha * tan2).sqrt())
+}
+
+pub fn ggx_g1(w: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + ggx_lambda(w, alpha))
+}
+
+// height-correlated Smith masking-shadowing
+pub fn ggx_g(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + ggx_lambda(wo, alpha) + ggx_lambda(wi, alpha))
+}
+
+// Heitz 2018, "Sampling the GGX Distribution of Visible Normals".
+pub fn ggx_sample_vndf(wo: Vec3, alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let vh = unit_vector(Vec3 {
+        x: alpha * wo.x,
+        y: alpha * wo.y,
+        z: wo.z,
+    });
+    let lensq = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if lensq > 0.0 {
+        Vec3 {
+            x: -vh.y,
+            y: vh.x,
+            z: 0.0,
+        } / lensq.sqrt()
+    } else {
+        Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    };
+    let t2 = vh ^ t1;
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+    unit_vector(Vec3 {
+        x: alpha * nh.x,
+        y: alpha * nh.y,
+        z: nh.z.max(1e-6),
+    })
+}
+
+// density of ggx_sample_vndf over half vectors
+pub fn ggx_vndf_pdf(wo: Vec3, h: Vec3, alpha: f64) -> f64 {
+    if wo.z <= 0.0 {
+        return 0.0;
+    }
+    ggx_g1(wo, alpha) * dot(wo, h).max(0.0) * ggx_d(h, alpha) / wo.z
+}
+
+// density over reflected directions wi = reflect(-wo, h)
+pub fn ggx_reflection_pdf(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return 0.0;
+    }
+    let h = unit_vector(wo + wi);
+    ggx_vndf_pdf(wo, h, alpha) / (4.0 * dot(wo, h))
+}
+
+// Fresnel reflectance of a conductor with complex index eta + i k.
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i = clamp(cos_i, 0.0, 1.0);
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+// Visible-normal sampling of GGX reflection around a shading frame.
+pub struct GgxReflectionPdf {
+    pub uvw: ONB,
+    pub wo: Vec3,
+    pub alpha: f64,
+}
+
+impl GgxReflectionPdf {
+    pub fn new(uvw: ONB, wo: Vec3, alpha: f64) -> Self {
+        GgxReflectionPdf { uvw, wo, alpha }
+    }
+}
+
+impl Pdf for GgxReflectionPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let wi = self.uvw.world_to_local(unit_vector(direction));
+        ggx_reflection_pdf(self.wo, wi, self.alpha)
+    }
+    fn generate(&self) -> Vec3 {
+        let h = ggx_sample_vndf(
+            self.wo,
+            self.alpha,
+            random_double_default(),
+            random_double_default(),
+        );
+        self.uvw.local_vec(reflect(-self.wo, h))
+    }
+}
+
+// Fresnel reflectance of a dielectric interface, eta = eta_t / eta_i.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i.min(1.0), eta)
+    };
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+// Refracts wo (pointing away from the surface, same side as m) through the
+// microfacet normal m. None on total internal reflection.
+pub fn refract_microfacet(wo: Vec3, m: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = dot(wo, m);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wo / eta + m * (cos_i / eta - cos_t))
+}
@@ -3,6 +3,25 @@
 use crate::random_double_default;
 use crate::*;
 
+pub mod coated;
+pub mod conductor;
+pub mod emissive_volume;
+pub mod measured;
+pub mod microfacet;
+pub mod principled;
+pub mod rough_dielectric;
+pub mod thin_film;
+pub mod toon;
+pub use coated::*;
+pub use conductor::*;
+pub use emissive_volume::*;
+pub use measured::*;
+pub use microfacet::*;
+pub use principled::*;
+pub use rough_dielectric::*;
+pub use thin_film::*;
+pub use toon::*;
+
 pub struct ScatterRecord {
     pub specular_ray: Ray,
     pub is_specular: bool,
@@ -20,6 +39,45 @@ pub trait Material: Send + Sync {
     fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
         0.0
     }
+    // BSDF times cosine for a non-specular scatter, the factor ray_color
+    // weights incoming light by
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        srec.attenuation * self.scattering_pdf(r_in, rec, scattered)
+    }
+    // total flux leaving a surface of the given area, used to pick lights
+    fn emitted_power(&self, _area: f64) -> f64 {
+        0.0
+    }
+    // Called by emitter geometry on construction with its surface area.
+    // Only the first call counts, so shapes made of several parts resolve
+    // their material once against the total before sharing it.
+    fn resolve_area(&mut self, _area: f64) {}
+    // light group the emission is written to, see LightGroups
+    fn light_group(&self) -> Option<&str> {
+        None
+    }
+    // how much more than on average an emitter facing `normal` sends along
+    // `dir`, for picking lights by what reaches a point
+    fn emission_weight(&self, _normal: Vec3, _dir: Vec3) -> f64 {
+        1.0
+    }
+    // Shade of a non-photorealistic surface, which ends the path. It is lit
+    // by the scene's key light alone, `key_light` giving the direction
+    // towards it or None where it is blocked. See Toon.
+    fn cel_shade(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _key_light: &dyn Fn() -> Option<Vec3>,
+    ) -> Option<Color> {
+        None
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -108,11 +166,7 @@ impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         let reflected = reflect(unit_vector(r_in.dir), rec.normal);
         Some(ScatterRecord {
-            specular_ray: Ray::new_tm(
-                rec.p,
-                reflected + random_in_unit_sphere() * self.fuzz,
-                r_in.tm,
-            ),
+            specular_ray: r_in.scattered(rec.p, reflected + random_in_unit_sphere() * self.fuzz),
             is_specular: true,
             attenuation: self.albedo,
             pdf_ptr: Box::new(DefaultPdf {}),
@@ -120,49 +174,192 @@ impl Material for Metal {
     }
 }
 
+// Wavelength dependence of an index of refraction, lambda in micrometres.
 #[derive(Clone, Copy)]
+pub enum Dispersion {
+    Constant,
+    // n = a + b / lambda^2
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum b_i lambda^2 / (lambda^2 - c_i)
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub fn ior(&self, lambda: f64) -> Option<f64> {
+        let l2 = lambda * lambda;
+        match *self {
+            Dispersion::Constant => None,
+            Dispersion::Cauchy { a, b } => Some(a + b / l2),
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f64 = (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum();
+                Some((1.0 + sum).sqrt())
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Dielectric {
+    // index used in RGB mode, at the sodium d-line for dispersive materials
     pub ir: f64,
+    // absorption coefficient per unit length inside the material
+    pub absorption: Color,
+    pub dispersion: Dispersion,
+    pub thin_film: Option<ThinFilm>,
+    // nested dielectrics only, see with_priority
+    pub medium: Option<NestedMedium>,
 }
 
+// wavelength in micrometres at which `ir` is quoted for dispersive glass
+const D_LINE: f64 = 0.5876;
+
 impl Dielectric {
     pub fn new(index_of_refraction: f64) -> Dielectric {
         Dielectric {
             ir: index_of_refraction,
+            absorption: Color::new(),
+            dispersion: Dispersion::Constant,
+            thin_film: None,
+            medium: None,
         }
     }
-    fn reflectance(&self, cosine: f64, ref_idx: f64) -> f64 {
-        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
-        r0 *= r0;
-        r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+    pub fn new_dispersive(dispersion: Dispersion) -> Dielectric {
+        Dielectric {
+            ir: dispersion.ior(D_LINE).unwrap_or(1.5),
+            absorption: Color::new(),
+            dispersion,
+            thin_film: None,
+            medium: None,
+        }
     }
-}
-
-impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        let attenuation = Vec3 {
+    pub fn new_cauchy(a: f64, b: f64) -> Dielectric {
+        Self::new_dispersive(Dispersion::Cauchy { a, b })
+    }
+    pub fn new_sellmeier(b: [f64; 3], c: [f64; 3]) -> Dielectric {
+        Self::new_dispersive(Dispersion::Sellmeier { b, c })
+    }
+    pub fn bk7() -> Dielectric {
+        Self::new_sellmeier(
+            [1.03961212, 0.231792344, 1.01046945],
+            [0.00600069867, 0.0200179144, 103.560653],
+        )
+    }
+    pub fn diamond() -> Dielectric {
+        Self::new_sellmeier([4.3356, 0.3306, 0.0], [0.01124, 0.030625, 0.0])
+    }
+    // Tracks the dielectric on the path's medium stack, so interfaces with
+    // other nested dielectrics use the index on both sides. Where volumes
+    // overlap, the highest priority one fills the overlap. The medium takes
+    // the index and absorption set so far, so call it last. Clones are the
+    // same medium; call it again for a separate one.
+    pub fn with_priority(mut self, priority: u32) -> Dielectric {
+        self.medium = Some(NestedMedium::new(
+            priority,
+            self.ir,
+            self.dispersion,
+            self.absorption,
+        ));
+        self
+    }
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Dielectric {
+        self.thin_film = Some(film);
+        self
+    }
+    // index of refraction seen by a ray, which depends on its wavelength
+    // in spectral mode
+    pub fn ior(&self, lambda: f64) -> f64 {
+        if lambda > 0.0 {
+            self.dispersion.ior(lambda * 1e-3).unwrap_or(self.ir)
+        } else {
+            self.ir
+        }
+    }
+    // `color` is the transmittance after travelling `distance` inside
+    pub fn new_absorbing(index_of_refraction: f64, color: Color, distance: f64) -> Dielectric {
+        let coefficient = |c: f64| -c.max(1e-6).ln() / distance;
+        Dielectric {
+            absorption: Color {
+                x: coefficient(color.x),
+                y: coefficient(color.y),
+                z: coefficient(color.z),
+            },
+            ..Dielectric::new(index_of_refraction)
+        }
+    }
+    // Beer-Lambert falloff along a ray that reached rec from inside
+    pub fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        let white = Color {
             x: 1.0,
             y: 1.0,
             z: 1.0,
         };
-        let refraction_ratio = match rec.front_face {
-            true => 1.0 / self.ir,
-            false => self.ir,
-        };
+        if rec.front_face || self.absorption.near_zero() {
+            return white;
+        }
+        let distance = rec.t * r_in.dir.length();
+        Color {
+            x: (-self.absorption.x * distance).exp(),
+            y: (-self.absorption.y * distance).exp(),
+            z: (-self.absorption.z * distance).exp(),
+        }
+    }
+    fn reflectance(&self, cosine: f64, ref_idx: f64) -> f64 {
+        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
+        r0 *= r0;
+        r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+    }
 
+    // Reflection or refraction from a medium of index n1 into one of n2.
+    // Refracted rays carry `refracted_media` on.
+    fn interface(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        n1: f64,
+        n2: f64,
+        attenuation: Color,
+        refracted_media: MediumStack,
+    ) -> Option<ScatterRecord> {
         let unit_direction = unit_vector(r_in.dir);
         let cos_theta = dot(-unit_direction, rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        if refraction_ratio * sin_theta > 1.0
-            || self.reflectance(cos_theta, refraction_ratio) > random_double_default()
-        {
+        let refraction_ratio = n1 / n2;
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let (reflects, attenuation) = match &self.thin_film {
+            Some(film) if !cannot_refract => {
+                let substrate = Color {
+                    x: n2,
+                    y: n2,
+                    z: n2,
+                };
+                let r = film.reflectance(cos_theta, n1, substrate, Color::new(), rec, r_in.lambda);
+                // pick a side by the mean reflectance and reweight per channel
+                let p = (r.x + r.y + r.z) / 3.0;
+                if random_double_default() < p {
+                    (true, attenuation * r / p)
+                } else {
+                    let white = Color {
+                        x: 1.0,
+                        y: 1.0,
+                        z: 1.0,
+                    };
+                    (false, attenuation * (white - r) / (1.0 - p))
+                }
+            }
+            _ => (
+                cannot_refract
+                    || self.reflectance(cos_theta, refraction_ratio) > random_double_default(),
+                attenuation,
+            ),
+        };
+        if reflects {
             // Some((
             //     attenuation,
             //     Ray::new_tm(rec.p, reflect(unit_direction, rec.normal), r_in.tm),
             // ))
             Some(ScatterRecord {
-                specular_ray: Ray::new_tm(rec.p, reflect(unit_direction, rec.normal), r_in.tm),
+                specular_ray: r_in.scattered(rec.p, reflect(unit_direction, rec.normal)),
                 is_specular: true,
                 attenuation,
                 pdf_ptr: Box::new(DefaultPdf {}),
@@ -176,54 +373,202 @@ impl Material for Dielectric {
             //         r_in.tm,
             //     ),
             // ))
+            let mut specular_ray =
+                r_in.scattered(rec.p, refract(unit_direction, rec.normal, refraction_ratio));
+            specular_ray.media = refracted_media;
             Some(ScatterRecord {
-                specular_ray: Ray::new_tm(
-                    rec.p,
-                    refract(unit_direction, rec.normal, refraction_ratio),
-                    r_in.tm,
-                ),
+                specular_ray,
                 is_specular: true,
                 attenuation,
                 pdf_ptr: Box::new(DefaultPdf {}),
             })
         }
+    }
+}
 
-        // let refracted = refract(unit_direction, rec.normal, refraction_ratio);
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let ir = self.ior(r_in.lambda);
+        let medium = match &self.medium {
+            Some(medium) => medium,
+            None => {
+                // not nested: the other side is assumed to be air
+                let (n1, n2) = if rec.front_face { (1.0, ir) } else { (ir, 1.0) };
+                let attenuation = self.transmittance(r_in, rec);
+                return self.interface(r_in, rec, n1, n2, attenuation, r_in.media);
+            }
+        };
 
-        // let scattered = Ray {
-        //     orig: rec.p,
-        //     dir: refracted,
-        // };
-        // Some((attenuation, scattered))
+        // absorption along the way is applied by ray_color
+        let attenuation = Color {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let current = r_in.media.top();
+        let mut crossed = r_in.media;
+        let (n1, n2, real) = if rec.front_face {
+            crossed.push(medium.entry(r_in.lambda));
+            let real = match current {
+                Some(m) => medium.priority >= m.priority,
+                None => true,
+            };
+            (current.map_or(1.0, |m| m.ior as f64), ir, real)
+        } else {
+            crossed.remove(medium.id);
+            let real = match current {
+                Some(m) => m.id == medium.id || !r_in.media.contains(medium.id),
+                None => true,
+            };
+            (ir, crossed.top().map_or(1.0, |m| m.ior as f64), real)
+        };
+        if !real {
+            // the boundary of a medium that a higher priority one overrides
+            let mut specular_ray = r_in.scattered(rec.p, r_in.dir);
+            specular_ray.media = crossed;
+            return Some(ScatterRecord {
+                specular_ray,
+                is_specular: true,
+                attenuation,
+                pdf_ptr: Box::new(DefaultPdf {}),
+            });
+        }
+        self.interface(r_in, rec, n1, n2, attenuation, crossed)
     }
 }
 
+// The radiance is `emit` times `scale`. An emitter given a total power
+// instead has it resolved against the area of the geometry it is attached
+// to, and `scale` then scales that power.
 #[derive(Clone)]
 pub struct DiffuseLight<T: Texture> {
     pub emit: T,
+    pub two_sided: bool,
+    pub scale: f64,
+    // power not yet resolved against the area of the emitter
+    pub watts: Option<f64>,
+    power_scale: f64,
+    // angular multiplier with its nadir along the surface normal and its
+    // C=0 plane through the given tangent
+    pub profile: Option<(Arc<IesProfile>, Vec3)>,
+    // cosine_average of the profile, 1 without one
+    profile_average: f64,
+    pub group: Option<String>,
 }
 
 impl DiffuseLight<SolidColor> {
     pub fn new(c: Color) -> Self {
-        DiffuseLight {
-            emit: SolidColor::new(c),
-        }
+        DiffuseLight::new_texture(SolidColor::new(c))
+    }
+    // `c` only sets the tint; the radiance is chosen so the surface emits
+    // `watts` in total
+    pub fn new_power(c: Color, watts: f64) -> Self {
+        DiffuseLight::new(c).with_power(watts)
+    }
+}
+impl DiffuseLight<BlackbodyTexture> {
+    // `intensity` is the luminance of the emitted radiance
+    pub fn new_temperature(kelvin: f64, intensity: f64) -> Self {
+        DiffuseLight::new_texture(BlackbodyTexture::new(kelvin, intensity))
     }
 }
 impl<T: Texture> DiffuseLight<T> {
     pub fn new_texture(a: T) -> Self {
-        DiffuseLight { emit: a }
+        DiffuseLight {
+            emit: a,
+            two_sided: false,
+            scale: 1.0,
+            watts: None,
+            power_scale: 1.0,
+            profile: None,
+            profile_average: 1.0,
+            group: None,
+        }
+    }
+    pub fn with_two_sided(mut self, two_sided: bool) -> Self {
+        self.two_sided = two_sided;
+        self
+    }
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+    // The power accounts for the profile, and a LightList picks the light
+    // by what the profile sends towards the shading point.
+    pub fn with_profile(mut self, profile: Arc<IesProfile>, tangent: Vec3) -> Self {
+        self.profile_average = profile.cosine_average();
+        self.profile = Some((profile, tangent));
+        self
+    }
+    // a two-sided emitter spreads the same power over both faces
+    pub fn with_power(mut self, watts: f64) -> Self {
+        self.watts = Some(watts);
+        self
+    }
+    fn sides(&self) -> f64 {
+        if self.two_sided {
+            2.0
+        } else {
+            1.0
+        }
     }
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
     fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
-        if rec.front_face {
-            self.emit.value(u, v, p)
+        assert!(
+            self.watts.is_none(),
+            "DiffuseLight::with_power used on geometry that never resolved its area."
+        );
+        if rec.front_face || self.two_sided {
+            let emit = self.emit.value(u, v, p) * self.scale * self.power_scale;
+            match &self.profile {
+                Some((profile, tangent)) => {
+                    let frame = ONB::build_from_wu(rec.normal, *tangent);
+                    emit * profile.multiplier_in(&frame, -r_in.dir)
+                }
+                None => emit,
+            }
         } else {
             Color::new()
         }
     }
+    fn emitted_power(&self, area: f64) -> f64 {
+        self.sides()
+            * self.scale
+            * self.power_scale
+            * self.profile_average
+            * PI
+            * area
+            * luminance(self.emit.average())
+    }
+    fn resolve_area(&mut self, area: f64) {
+        if let Some(watts) = self.watts.take() {
+            let unit_power =
+                self.sides() * self.profile_average * PI * area * luminance(self.emit.average());
+            self.power_scale = if unit_power > 0.0 {
+                watts / unit_power
+            } else {
+                0.0
+            };
+        }
+    }
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+    fn emission_weight(&self, normal: Vec3, dir: Vec3) -> f64 {
+        match &self.profile {
+            Some((profile, tangent)) if self.profile_average > 0.0 => {
+                let frame = ONB::build_from_wu(normal, *tangent);
+                profile.multiplier_in(&frame, dir) / self.profile_average
+            }
+            _ => 1.0,
+        }
+    }
 }
 
 pub struct Isotropic<T: Texture> {
@@ -252,10 +597,59 @@ impl<T: Texture> Material for Isotropic<T> {
     // }
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         Some(ScatterRecord {
-            specular_ray: Ray::new_tm(rec.p, random_in_unit_sphere(), r_in.tm),
-            is_specular: true,
+            specular_ray: *r_in,
+            is_specular: false,
             attenuation: self.albedo.value(rec.u, rec.v, rec.p),
-            pdf_ptr: Box::new(DefaultPdf {}),
+            pdf_ptr: Box::new(HenyeyGreensteinPdf::new(r_in.dir, 0.0)),
         })
     }
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+// Anisotropic phase function for media, a Henyey-Greenstein lobe or a
+// blend of a forward (g1) and a backward (g2) one.
+#[derive(Clone)]
+pub struct HenyeyGreenstein<T: Texture> {
+    pub albedo: T,
+    pub g1: f64,
+    pub g2: f64,
+    pub w: f64,
+}
+
+impl HenyeyGreenstein<SolidColor> {
+    pub fn new_color(c: Color, g: f64) -> Self {
+        HenyeyGreenstein::new(SolidColor::new(c), g)
+    }
+}
+impl<T: Texture> HenyeyGreenstein<T> {
+    pub fn new(a: T, g: f64) -> Self {
+        HenyeyGreenstein::new_double(a, g, 0.0, 1.0)
+    }
+    pub fn new_double(a: T, g1: f64, g2: f64, w: f64) -> Self {
+        HenyeyGreenstein {
+            albedo: a,
+            g1: clamp(g1, -0.99, 0.99),
+            g2: clamp(g2, -0.99, 0.99),
+            w: clamp(w, 0.0, 1.0),
+        }
+    }
+    fn pdf(&self, r_in: &Ray) -> HenyeyGreensteinPdf {
+        HenyeyGreensteinPdf::new_double(r_in.dir, self.g1, self.g2, self.w)
+    }
+}
+
+impl<T: Texture> Material for HenyeyGreenstein<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            specular_ray: *r_in,
+            is_specular: false,
+            attenuation: self.albedo.value(rec.u, rec.v, rec.p),
+            pdf_ptr: Box::new(self.pdf(r_in)),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.pdf(r_in).value(scattered.dir)
+    }
 }
@@ -0,0 +1,236 @@
+use crate::*;
+
+// fixed gloss of the clear coat lobe
+const CLEARCOAT_ALPHA: f64 = 0.05;
+// keeps the specular lobes away from a delta, which the pdf pipeline
+// cannot represent
+const PRINCIPLED_MIN_ALPHA: f64 = 0.002;
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1.0 - clamp(cosine, 0.0, 1.0)).powi(5)
+}
+
+fn schlick(f0: Color, cosine: f64) -> Color {
+    let w = schlick_weight(cosine);
+    f0 * (1.0 - w) + Color { x: w, y: w, z: w }
+}
+
+// Schlick with the F0 of a polyurethane coat (ior 1.5)
+fn clearcoat_fresnel(cosine: f64) -> f64 {
+    0.04 + 0.96 * schlick_weight(cosine)
+}
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+fn white() -> Color {
+    Color {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    }
+}
+
+pub fn constant_texture(v: f64) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(Color { x: v, y: v, z: v }))
+}
+
+// Burley 2012 "Physically Based Shading at Disney" style material. Scalar
+// parameters are read from the first channel of their texture.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    pub specular: Arc<dyn Texture>,
+    pub specular_tint: Arc<dyn Texture>,
+    pub sheen: Arc<dyn Texture>,
+    pub clearcoat: Arc<dyn Texture>,
+    pub transmission: Arc<dyn Texture>,
+    pub emission: Arc<dyn Texture>,
+    pub ir: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Arc<dyn Texture>) -> Self {
+        Principled {
+            base_color,
+            metallic: constant_texture(0.0),
+            roughness: constant_texture(0.5),
+            specular: constant_texture(0.5),
+            specular_tint: constant_texture(0.0),
+            sheen: constant_texture(0.0),
+            clearcoat: constant_texture(0.0),
+            transmission: constant_texture(0.0),
+            emission: constant_texture(0.0),
+            ir: 1.5,
+        }
+    }
+
+    pub fn new_color(base_color: Color) -> Self {
+        Self::new(Arc::new(SolidColor::new(base_color)))
+    }
+
+    fn lobes(&self, r_in: &Ray, rec: &HitRecord) -> PrincipledLobes {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let base = self.base_color.value(u, v, p);
+        let base_luminance = luminance(base);
+        let tint = if base_luminance > 0.0 {
+            base / base_luminance
+        } else {
+            white()
+        };
+        let specular = self.specular.value(u, v, p).x;
+        let specular_tint = self.specular_tint.value(u, v, p).x;
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        let alpha = roughness_to_alpha(self.roughness.value(u, v, p).x).max(PRINCIPLED_MIN_ALPHA);
+        let eta = if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        };
+        PrincipledLobes {
+            uvw,
+            wo,
+            base,
+            metallic: clamp(self.metallic.value(u, v, p).x, 0.0, 1.0),
+            transmission: clamp(self.transmission.value(u, v, p).x, 0.0, 1.0),
+            alpha,
+            specular_f0: lerp(white(), tint, specular_tint) * (0.08 * specular),
+            sheen: lerp(white(), tint, 0.5) * self.sheen.value(u, v, p).x,
+            clearcoat: self.clearcoat.value(u, v, p).x,
+            dielectric: RoughDielectricPdf::new(uvw, wo, alpha, eta),
+        }
+    }
+}
+
+// Every parameter of a Principled material evaluated at one hit point.
+struct PrincipledLobes {
+    uvw: ONB,
+    wo: Vec3,
+    base: Color,
+    metallic: f64,
+    transmission: f64,
+    alpha: f64,
+    specular_f0: Color,
+    sheen: Color,
+    clearcoat: f64,
+    dielectric: RoughDielectricPdf,
+}
+
+impl PrincipledLobes {
+    fn diffuse_weight(&self) -> f64 {
+        (1.0 - self.metallic) * (1.0 - self.transmission)
+    }
+
+    fn transmission_weight(&self) -> f64 {
+        (1.0 - self.metallic) * self.transmission
+    }
+
+    // BSDF times |cos wi|, wi in the local frame
+    fn eval(&self, wi: Vec3) -> Color {
+        let wo = self.wo;
+        let mut result = Color::new();
+        if wi.z > 0.0 && wo.z > 0.0 {
+            let h = unit_vector(wo + wi);
+            let cos_d = dot(wi, h);
+
+            let fd90 = 0.5 + 2.0 * self.alpha.sqrt() * cos_d * cos_d;
+            let retro = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+            let diffuse = self.base * (retro / PI) + self.sheen * schlick_weight(cos_d);
+            result += diffuse * (self.diffuse_weight() * wi.z);
+
+            let f0 = lerp(self.specular_f0, self.base, self.metallic);
+            let specular = schlick(f0, dot(wo, h))
+                * (ggx_d(h, self.alpha) * ggx_g(wo, wi, self.alpha) / (4.0 * wo.z));
+            result += specular * (1.0 - self.transmission_weight());
+
+            let clearcoat = 0.25
+                * self.clearcoat
+                * clearcoat_fresnel(dot(wo, h))
+                * ggx_d(h, CLEARCOAT_ALPHA)
+                * ggx_g(wo, wi, CLEARCOAT_ALPHA)
+                / (4.0 * wo.z);
+            result += white() * clearcoat;
+        }
+        if self.transmission_weight() > 0.0 {
+            let value = self.dielectric.bsdf_cos(wi) * self.transmission_weight();
+            // only light passing through the material picks up its colour
+            result += if wi.z < 0.0 {
+                self.base * value
+            } else {
+                white() * value
+            };
+        }
+        result
+    }
+
+    fn pdf(&self) -> WeightedMixturePdf {
+        let fresnel = luminance(schlick(self.specular_f0, self.wo.z));
+        let opaque = self.diffuse_weight();
+        let mut pdf = WeightedMixturePdf::new();
+        pdf.add(
+            opaque * (1.0 - fresnel),
+            Box::new(CosinePdf::new(self.uvw.w())),
+        );
+        pdf.add(
+            opaque * fresnel + self.metallic,
+            Box::new(GgxReflectionPdf::new(self.uvw, self.wo, self.alpha)),
+        );
+        pdf.add(
+            0.25 * self.clearcoat * clearcoat_fresnel(self.wo.z),
+            Box::new(GgxReflectionPdf::new(self.uvw, self.wo, CLEARCOAT_ALPHA)),
+        );
+        pdf.add(
+            self.transmission_weight(),
+            Box::new(RoughDielectricPdf::new(
+                self.uvw,
+                self.wo,
+                self.alpha,
+                self.dielectric.eta,
+            )),
+        );
+        pdf
+    }
+}
+
+impl Material for Principled {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        if rec.front_face {
+            self.emission.value(u, v, p)
+        } else {
+            Color::new()
+        }
+    }
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let lobes = self.lobes(r_in, rec);
+        if lobes.wo.z <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            specular_ray: *r_in,
+            is_specular: false,
+            attenuation: white(),
+            pdf_ptr: Box::new(lobes.pdf()),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.lobes(r_in, rec).pdf().value(scattered.dir)
+    }
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let lobes = self.lobes(r_in, rec);
+        lobes.eval(lobes.uvw.world_to_local(unit_vector(scattered.dir)))
+    }
+    fn emitted_power(&self, area: f64) -> f64 {
+        PI * area * luminance(self.emission.average())
+    }
+}
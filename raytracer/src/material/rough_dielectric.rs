@@ -0,0 +1,206 @@
+use crate::*;
+
+// Walter et al. 2007, "Microfacet Models for Refraction through Rough
+// Surfaces", with GGX visible-normal sampling. Vectors are local to the
+// shading frame, whose z axis faces wo; eta is the index ratio across it.
+pub struct RoughDielectricPdf {
+    pub uvw: ONB,
+    pub wo: Vec3,
+    pub alpha: f64,
+    pub eta: f64,
+}
+
+impl RoughDielectricPdf {
+    pub fn new(uvw: ONB, wo: Vec3, alpha: f64, eta: f64) -> Self {
+        RoughDielectricPdf {
+            uvw,
+            wo,
+            alpha,
+            eta,
+        }
+    }
+
+    // microfacet normal that reflects wo into wi, if one can be sampled
+    fn reflection_half(&self, wi: Vec3) -> Option<Vec3> {
+        let h = self.wo + wi;
+        if h.length_squared() == 0.0 {
+            return None;
+        }
+        let h = unit_vector(h);
+        if h.z <= 0.0 || dot(self.wo, h) <= 0.0 {
+            return None;
+        }
+        Some(h)
+    }
+
+    // microfacet normal that refracts wo into wi, if one can be sampled
+    fn transmission_half(&self, wi: Vec3) -> Option<Vec3> {
+        let h = self.wo + wi * self.eta;
+        if h.length_squared() == 0.0 {
+            return None;
+        }
+        let mut h = unit_vector(h);
+        if dot(self.wo, h) < 0.0 {
+            h = -h;
+        }
+        if h.z <= 0.0 || dot(wi, h) >= 0.0 {
+            return None;
+        }
+        Some(h)
+    }
+
+    // BSDF times |cos wi| for radiance
+    pub fn bsdf_cos(&self, wi: Vec3) -> f64 {
+        let wo = self.wo;
+        if wi.z > 0.0 {
+            match self.reflection_half(wi) {
+                Some(wm) => {
+                    let f = fresnel_dielectric(dot(wo, wm), self.eta);
+                    ggx_d(wm, self.alpha) * ggx_g(wo, wi, self.alpha) * f / (4.0 * wo.z)
+                }
+                None => 0.0,
+            }
+        } else if wi.z < 0.0 {
+            match self.transmission_half(wi) {
+                Some(wm) => {
+                    let f = fresnel_dielectric(dot(wo, wm), self.eta);
+                    let denom = dot(wi, wm) + dot(wo, wm) / self.eta;
+                    ggx_d(wm, self.alpha)
+                        * ggx_g(wo, wi, self.alpha)
+                        * (1.0 - f)
+                        * (dot(wi, wm) * dot(wo, wm)).abs()
+                        / (denom * denom * wo.z * self.eta * self.eta)
+                }
+                None => 0.0,
+            }
+        } else {
+            0.0
+        }
+    }
+
+    // Density of generate(). A rough reflection can end up below the
+    // surface and a refraction above it, so both lobes are summed no matter
+    // which side wi is on; bsdf_cos is zero for those directions.
+    pub fn local_value(&self, wi: Vec3) -> f64 {
+        let wo = self.wo;
+        let mut pdf = 0.0;
+        if let Some(wm) = self.reflection_half(wi) {
+            let f = fresnel_dielectric(dot(wo, wm), self.eta);
+            pdf += ggx_vndf_pdf(wo, wm, self.alpha) / (4.0 * dot(wo, wm)) * f;
+        }
+        if let Some(wm) = self.transmission_half(wi) {
+            let f = fresnel_dielectric(dot(wo, wm), self.eta);
+            let denom = dot(wi, wm) + dot(wo, wm) / self.eta;
+            pdf +=
+                ggx_vndf_pdf(wo, wm, self.alpha) * dot(wi, wm).abs() / (denom * denom) * (1.0 - f);
+        }
+        pdf
+    }
+}
+
+impl Pdf for RoughDielectricPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.local_value(self.uvw.world_to_local(unit_vector(direction)))
+    }
+    fn generate(&self) -> Vec3 {
+        let wm = ggx_sample_vndf(
+            self.wo,
+            self.alpha,
+            random_double_default(),
+            random_double_default(),
+        );
+        let f = fresnel_dielectric(dot(self.wo, wm), self.eta);
+        let wi = if random_double_default() < f {
+            reflect(-self.wo, wm)
+        } else {
+            refract_microfacet(self.wo, wm, self.eta).unwrap_or_else(|| reflect(-self.wo, wm))
+        };
+        self.uvw.local_vec(wi)
+    }
+}
+
+// Frosted glass. The roughness is read from the first channel of a texture
+// and falls back to the smooth Dielectric where it is close to zero.
+#[derive(Clone)]
+pub struct RoughDielectric<T: Texture> {
+    pub ir: f64,
+    pub roughness: T,
+    pub absorption: Color,
+}
+
+impl RoughDielectric<SolidColor> {
+    pub fn new(index_of_refraction: f64, roughness: f64) -> Self {
+        RoughDielectric::new_texture(
+            index_of_refraction,
+            SolidColor::new(Color {
+                x: roughness,
+                y: roughness,
+                z: roughness,
+            }),
+        )
+    }
+}
+
+impl<T: Texture> RoughDielectric<T> {
+    pub fn new_texture(index_of_refraction: f64, roughness: T) -> Self {
+        RoughDielectric {
+            ir: index_of_refraction,
+            roughness,
+            absorption: Color::new(),
+        }
+    }
+    // `color` is the transmittance after travelling `distance` inside
+    pub fn with_absorption(mut self, color: Color, distance: f64) -> Self {
+        self.absorption = Dielectric::new_absorbing(self.ir, color, distance).absorption;
+        self
+    }
+    fn smooth(&self) -> Dielectric {
+        Dielectric {
+            absorption: self.absorption,
+            ..Dielectric::new(self.ir)
+        }
+    }
+
+    fn local_pdf(&self, r_in: &Ray, rec: &HitRecord) -> RoughDielectricPdf {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.world_to_local(-unit_vector(r_in.dir));
+        let alpha = roughness_to_alpha(self.roughness.value(rec.u, rec.v, rec.p).x);
+        let eta = if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        };
+        RoughDielectricPdf::new(uvw, wo, alpha, eta)
+    }
+}
+
+impl<T: Texture> Material for RoughDielectric<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let pdf = self.local_pdf(r_in, rec);
+        if pdf.alpha < MIN_ALPHA {
+            return self.smooth().scatter(r_in, rec);
+        }
+        if pdf.wo.z <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            specular_ray: *r_in,
+            is_specular: false,
+            attenuation: self.smooth().transmittance(r_in, rec),
+            pdf_ptr: Box::new(pdf),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.local_pdf(r_in, rec).value(scattered.dir)
+    }
+    fn scattering_value(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let pdf = self.local_pdf(r_in, rec);
+        srec.attenuation * pdf.bsdf_cos(pdf.uvw.world_to_local(unit_vector(scattered.dir)))
+    }
+}
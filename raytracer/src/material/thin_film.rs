@@ -0,0 +1,190 @@
+use crate::*;
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+    fn add(self, o: Complex) -> Complex {
+        Complex::new(self.re + o.re, self.im + o.im)
+    }
+    fn sub(self, o: Complex) -> Complex {
+        Complex::new(self.re - o.re, self.im - o.im)
+    }
+    fn mul(self, o: Complex) -> Complex {
+        Complex::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+    fn div(self, o: Complex) -> Complex {
+        let d = o.norm_sqr();
+        Complex::new(
+            (self.re * o.re + self.im * o.im) / d,
+            (self.im * o.re - self.re * o.im) / d,
+        )
+    }
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+    // principal root, which keeps the imaginary part non-negative
+    fn sqrt(self) -> Complex {
+        let r = self.norm_sqr().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+    fn exp_i(phase: f64) -> Complex {
+        Complex::new(phase.cos(), phase.sin())
+    }
+}
+
+// cosine of the angle inside a medium of index n, by Snell's law
+fn snell_cos(n1_sin: f64, n: Complex) -> Complex {
+    let s = Complex::new(n1_sin, 0.0).div(n);
+    Complex::new(1.0, 0.0).sub(s.mul(s)).sqrt()
+}
+
+// s and p amplitude reflection coefficients between two media
+fn fresnel_amplitudes(na: Complex, cos_a: Complex, nb: Complex, cos_b: Complex) -> [Complex; 2] {
+    let s = na
+        .mul(cos_a)
+        .sub(nb.mul(cos_b))
+        .div(na.mul(cos_a).add(nb.mul(cos_b)));
+    let p = nb
+        .mul(cos_a)
+        .sub(na.mul(cos_b))
+        .div(nb.mul(cos_a).add(na.mul(cos_b)));
+    [s, p]
+}
+
+// Reflectance of a film of index n2 and thickness d (nm) between a medium
+// of index n1 and a substrate n3 + i k3, summing the Airy series of the
+// light bouncing inside the film.
+pub fn thin_film_reflectance(
+    cos_i: f64,
+    n1: f64,
+    n2: f64,
+    d: f64,
+    n3: f64,
+    k3: f64,
+    lambda: f64,
+) -> f64 {
+    let cos_i = clamp(cos_i, 0.0, 1.0);
+    let n1_sin = n1 * (1.0 - cos_i * cos_i).sqrt();
+    let (c1, c2, c3) = (
+        Complex::new(n1, 0.0),
+        Complex::new(n2, 0.0),
+        Complex::new(n3, k3),
+    );
+    let cos1 = Complex::new(cos_i, 0.0);
+    let cos2 = snell_cos(n1_sin, c2);
+    let cos3 = snell_cos(n1_sin, c3);
+    let r12 = fresnel_amplitudes(c1, cos1, c2, cos2);
+    let r23 = fresnel_amplitudes(c2, cos2, c3, cos3);
+    // phase difference of one round trip through the film
+    let delta = c2.mul(cos2).mul(Complex::new(4.0 * PI * d / lambda, 0.0));
+    // a complex delta means an evanescent wave under total internal reflection
+    let phase = Complex::exp_i(delta.re).mul(Complex::new((-delta.im).exp(), 0.0));
+    let one = Complex::new(1.0, 0.0);
+    let mut reflectance = 0.0;
+    for i in 0..2 {
+        let r = r12[i]
+            .add(r23[i].mul(phase))
+            .div(one.add(r12[i].mul(r23[i]).mul(phase)));
+        reflectance += 0.5 * r.norm_sqr();
+    }
+    clamp(reflectance, 0.0, 1.0)
+}
+
+// wavelengths the RGB substrate indices are quoted at, as in Conductor
+const CHANNEL_LAMBDA: [f64; 3] = [650.0, 550.0, 450.0];
+const FILM_LAMBDA_MIN: f64 = 380.0;
+const FILM_LAMBDA_MAX: f64 = 780.0;
+const FILM_LAMBDA_STEP: f64 = 10.0;
+
+// piecewise linear in wavelength through the three channel values
+fn channel_at(c: Color, lambda: f64) -> f64 {
+    let v = [c.x, c.y, c.z];
+    if lambda >= CHANNEL_LAMBDA[0] {
+        v[0]
+    } else if lambda <= CHANNEL_LAMBDA[2] {
+        v[2]
+    } else {
+        let i = if lambda >= CHANNEL_LAMBDA[1] { 0 } else { 1 };
+        let t = (CHANNEL_LAMBDA[i] - lambda) / (CHANNEL_LAMBDA[i] - CHANNEL_LAMBDA[i + 1]);
+        v[i] * (1.0 - t) + v[i + 1] * t
+    }
+}
+
+// Thin coating on a surface, as on soap bubbles, oil slicks and anodised
+// metal. The thickness in nanometres is read from the first channel of a
+// texture.
+#[derive(Clone)]
+pub struct ThinFilm {
+    pub ior: f64,
+    pub thickness: Arc<dyn Texture>,
+}
+
+impl ThinFilm {
+    pub fn new(index_of_refraction: f64, thickness: f64) -> Self {
+        Self::new_texture(index_of_refraction, constant_texture(thickness))
+    }
+    pub fn new_texture(index_of_refraction: f64, thickness: Arc<dyn Texture>) -> Self {
+        ThinFilm {
+            ior: index_of_refraction,
+            thickness,
+        }
+    }
+
+    // Reflectance at rec between a medium of index n1 and a substrate with
+    // RGB indices eta + i k. In spectral mode the value at the ray's
+    // wavelength is returned in every channel; otherwise the spectrum is
+    // integrated into linear sRGB, with a flat spectrum mapping to white.
+    pub fn reflectance(
+        &self,
+        cos_i: f64,
+        n1: f64,
+        eta: Color,
+        k: Color,
+        rec: &HitRecord,
+        lambda: f64,
+    ) -> Color {
+        let d = self.thickness.value(rec.u, rec.v, rec.p).x.max(0.0);
+        let at = |l: f64| {
+            thin_film_reflectance(
+                cos_i,
+                n1,
+                self.ior,
+                d,
+                channel_at(eta, l),
+                channel_at(k, l),
+                l,
+            )
+        };
+        if lambda > 0.0 {
+            let r = at(lambda);
+            return Color { x: r, y: r, z: r };
+        }
+        let mut xyz = Vec3::new();
+        let mut white = Vec3::new();
+        let mut l = FILM_LAMBDA_MIN;
+        while l <= FILM_LAMBDA_MAX {
+            xyz += cie_xyz(l) * at(l);
+            white += cie_xyz(l);
+            l += FILM_LAMBDA_STEP;
+        }
+        let rgb = xyz_to_linear_srgb(xyz);
+        let white = xyz_to_linear_srgb(white);
+        Color {
+            x: clamp(rgb.x / white.x, 0.0, 1.0),
+            y: clamp(rgb.y / white.y, 0.0, 1.0),
+            z: clamp(rgb.z / white.z, 0.0, 1.0),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::*;
+
+// Cel-shaded surface for non-photorealistic renders. Lighting from the
+// scene's key light, shadows included, is quantized into `bands` flat tones
+// between `shadow` and full brightness, and a rim light outlines the parts
+// of the silhouette that turn away from the viewer. Nothing is scattered,
+// so a path ends at the first toon surface it meets.
+#[derive(Clone)]
+pub struct Toon<T: Texture> {
+    pub albedo: T,
+    pub bands: u32,
+    pub shadow: f64,
+    pub rim_color: Color,
+    // the rim covers points seen at a cosine below this, 0 for none
+    pub rim_width: f64,
+}
+
+impl Toon<SolidColor> {
+    pub fn new_color(c: Color) -> Self {
+        Toon::new(SolidColor::new(c))
+    }
+}
+
+impl<T: Texture> Toon<T> {
+    pub fn new(albedo: T) -> Self {
+        Toon {
+            albedo,
+            bands: 3,
+            shadow: 0.35,
+            rim_color: Color {
+                x: 0.25,
+                y: 0.25,
+                z: 0.25,
+            },
+            rim_width: 0.2,
+        }
+    }
+    pub fn with_bands(mut self, bands: u32, shadow: f64) -> Self {
+        self.bands = bands.max(1);
+        self.shadow = shadow;
+        self
+    }
+    pub fn with_rim(mut self, rim_color: Color, rim_width: f64) -> Self {
+        self.rim_color = rim_color;
+        self.rim_width = rim_width;
+        self
+    }
+
+    // brightness of the band cos_l falls into, the lit side starting at 1
+    fn tone(&self, cos_l: f64) -> f64 {
+        if self.bands <= 1 {
+            return if cos_l > 0.0 { 1.0 } else { self.shadow };
+        }
+        let band = ((cos_l.max(0.0) * self.bands as f64) as u32).min(self.bands - 1);
+        self.shadow + (1.0 - self.shadow) * band as f64 / (self.bands - 1) as f64
+    }
+}
+
+impl<T: Texture> Material for Toon<T> {
+    fn cel_shade(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        key_light: &dyn Fn() -> Option<Vec3>,
+    ) -> Option<Color> {
+        // a blocked key light counts as one behind the surface
+        let cos_l = key_light().map_or(-1.0, |dir| dot(rec.normal, dir));
+        let cos_v = dot(rec.normal, -unit_vector(r_in.dir)).max(0.0);
+        let rim = if cos_v < self.rim_width {
+            self.rim_color
+        } else {
+            Color::new()
+        };
+        Some(self.albedo.value(rec.u, rec.v, rec.p) * self.tone(cos_l) + rim)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::*;
+
+// Piecewise-constant distribution over [0, 1), built from non-negative weights.
+// Falls back to a uniform distribution when every weight is zero.
+#[derive(Clone)]
+pub struct Distribution1D {
+    pub func: Vec<f64>,
+    pub cdf: Vec<f64>,
+    pub func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(f: &[f64]) -> Self {
+        let n = f.len();
+        let func: Vec<f64> = f.iter().map(|x| x.max(0.0)).collect();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f64;
+        }
+        let func_int = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if func_int == 0.0 {
+                i as f64 / n as f64
+            } else {
+                *c / func_int
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    fn find_interval(&self, u: f64) -> usize {
+        // last index i with cdf[i] <= u, clamped to a valid segment
+        let mut lo = 0;
+        let mut hi = self.count();
+        while lo + 1 < hi {
+            let mid = (lo + hi) / 2;
+            if self.cdf[mid] <= u {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    // returns (x in [0, 1), density at x, segment index)
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self.find_interval(u);
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+        let pdf = if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            1.0
+        };
+        ((offset as f64 + du) / self.count() as f64, pdf, offset)
+    }
+
+    // returns (segment index, probability of choosing it)
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let offset = self.find_interval(u);
+        (offset, self.discrete_pdf(offset))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f64 {
+        if self.func_int > 0.0 {
+            self.func[index] / (self.func_int * self.count() as f64)
+        } else {
+            1.0 / self.count() as f64
+        }
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            1.0
+        }
+    }
+}
+
+// Piecewise-constant distribution over [0, 1)^2, func is stored row by row
+// (nv rows of nu entries). Sampled as a marginal over rows and a
+// conditional distribution inside the chosen row.
+#[derive(Clone)]
+pub struct Distribution2D {
+    pub conditional: Vec<Distribution1D>,
+    pub marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> = (0..nv)
+            .map(|v| Distribution1D::new(&func[v * nu..(v + 1) * nu]))
+            .collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|d| d.func_int).collect();
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    // returns ((u, v), density with respect to area in [0, 1)^2)
+    pub fn sample_continuous(&self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf1, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf0, _) = self.conditional[row].sample_continuous(u0);
+        ((u, v), pdf0 * pdf1)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let nv = self.marginal.count();
+        let row = ((v * nv as f64) as usize).min(nv - 1);
+        self.marginal.pdf(v) * self.conditional[row].pdf(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_matches_pdf_1d() {
+        let d = Distribution1D::new(&[1.0, 3.0, 0.0, 4.0]);
+        let n = 4000;
+        let mut counts = [0.0; 4];
+        for k in 0..n {
+            let (x, pdf, index) = d.sample_continuous((k as f64 + 0.5) / n as f64);
+            assert_eq!(index, (x * 4.0) as usize);
+            assert!((pdf - d.pdf(x)).abs() < 1e-12);
+            counts[index] += 1.0 / n as f64;
+        }
+        let total: f64 = (0..4).map(|i| d.discrete_pdf(i)).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+        for (i, count) in counts.iter().enumerate() {
+            assert!((count - d.discrete_pdf(i)).abs() < 1e-3);
+        }
+        assert_eq!(counts[2], 0.0);
+    }
+
+    #[test]
+    fn zero_weights_fall_back_to_uniform() {
+        let d = Distribution1D::new(&[0.0, 0.0, 0.0, 0.0]);
+        for k in 0..16 {
+            let u = (k as f64 + 0.5) / 16.0;
+            let (x, pdf, _) = d.sample_continuous(u);
+            assert!((x - u).abs() < 1e-12);
+            assert_eq!(pdf, 1.0);
+        }
+        assert_eq!(d.discrete_pdf(3), 0.25);
+        assert_eq!(d.pdf(0.7), 1.0);
+    }
+
+    #[test]
+    fn sampling_matches_pdf_2d() {
+        let (nu, nv) = (3, 2);
+        let d = Distribution2D::new(&[1.0, 2.0, 0.0, 4.0, 1.0, 2.0], nu, nv);
+        let n = 200;
+        let mut counts = vec![0.0; nu * nv];
+        for i in 0..n {
+            for j in 0..n {
+                let u0 = (i as f64 + 0.5) / n as f64;
+                let u1 = (j as f64 + 0.5) / n as f64;
+                let ((u, v), pdf) = d.sample_continuous(u0, u1);
+                assert!((pdf - d.pdf(u, v)).abs() < 1e-12);
+                let cell = (v * nv as f64) as usize * nu + (u * nu as f64) as usize;
+                counts[cell] += 1.0 / (n * n) as f64;
+            }
+        }
+        let mut integral = 0.0;
+        for (cell, count) in counts.iter().enumerate() {
+            let u = ((cell % nu) as f64 + 0.5) / nu as f64;
+            let v = ((cell / nu) as f64 + 0.5) / nv as f64;
+            let mass = d.pdf(u, v) / (nu * nv) as f64;
+            integral += mass;
+            // the strata of u1 only resolve the marginal to 1 / n
+            assert!((count - mass).abs() < 5e-3);
+        }
+        assert!((integral - 1.0).abs() < 1e-12);
+    }
+}
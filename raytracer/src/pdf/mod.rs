@@ -1,7 +1,13 @@
 use crate::*;
 
+pub mod distribution;
 pub mod onb;
+pub mod phase;
+pub mod spherical_rect;
+pub use distribution::*;
 pub use onb::*;
+pub use phase::*;
+pub use spherical_rect::*;
 pub trait Pdf {
     fn value(&self, direction: Vec3) -> f64;
     fn generate(&self) -> Vec3;
@@ -108,3 +114,51 @@ impl<'a> Pdf for MixturePdf<'a> {
         }
     }
 }
+
+// Owning mixture of any number of pdfs, each picked in proportion to its
+// weight. Lobes with no weight are left out.
+pub struct WeightedMixturePdf {
+    pub pdfs: Vec<(f64, Box<dyn Pdf>)>,
+    total: f64,
+}
+
+impl WeightedMixturePdf {
+    pub fn new() -> Self {
+        WeightedMixturePdf {
+            pdfs: Vec::new(),
+            total: 0.0,
+        }
+    }
+    pub fn add(&mut self, weight: f64, pdf: Box<dyn Pdf>) {
+        if weight > 0.0 {
+            self.total += weight;
+            self.pdfs.push((weight, pdf));
+        }
+    }
+}
+
+impl Pdf for WeightedMixturePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        self.pdfs
+            .iter()
+            .map(|(weight, pdf)| weight * pdf.value(direction))
+            .sum::<f64>()
+            / self.total
+    }
+    fn generate(&self) -> Vec3 {
+        let mut u = random_double_default() * self.total;
+        for (weight, pdf) in &self.pdfs {
+            if u < *weight {
+                return pdf.generate();
+            }
+            u -= weight;
+        }
+        match self.pdfs.last() {
+            Some((_, pdf)) => pdf.generate(),
+            None => Vec3::new(),
+        }
+    }
+}
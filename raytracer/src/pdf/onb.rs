@@ -1,6 +1,7 @@
 use crate::*;
 
 #[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
 pub struct ONB {
     pub axis: [Vec3; 3],
 }
@@ -26,6 +27,14 @@ impl ONB {
     pub fn local_vec(&self, a: Vec3) -> Vec3 {
         self.u() * a.x + self.v() * a.y + self.w() * a.z
     }
+    // inverse of local_vec
+    pub fn world_to_local(&self, a: Vec3) -> Vec3 {
+        Vec3 {
+            x: dot(a, self.u()),
+            y: dot(a, self.v()),
+            z: dot(a, self.w()),
+        }
+    }
 
     pub fn build_from_w(n: Vec3) -> ONB {
         let w = unit_vector(n);
@@ -46,4 +55,17 @@ impl ONB {
         let u = w ^ v;
         ONB { axis: [u, v, w] }
     }
+
+    // frame around n whose u axis is `tangent` made perpendicular to it
+    pub fn build_from_wu(n: Vec3, tangent: Vec3) -> ONB {
+        let w = unit_vector(n);
+        let t = tangent - w * dot(tangent, w);
+        if t.near_zero() {
+            return ONB::build_from_w(n);
+        }
+        let u = unit_vector(t);
+        ONB {
+            axis: [u, u ^ w, w],
+        }
+    }
 }
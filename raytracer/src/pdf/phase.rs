@@ -0,0 +1,61 @@
+use crate::*;
+
+// Henyey-Greenstein phase function. cos_theta is measured from the
+// direction of travel, so g > 0 scatters forward and g < 0 backward.
+pub fn hg_phase(cos_theta: f64, g: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.max(1e-12).sqrt())
+}
+
+// cosine of a scattering angle distributed by hg_phase
+pub fn hg_sample_cos(g: f64, u: f64) -> f64 {
+    if g.abs() < 1e-3 {
+        return 1.0 - 2.0 * u;
+    }
+    let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+    clamp((1.0 + g * g - s * s) / (2.0 * g), -1.0, 1.0)
+}
+
+// Blend of a forward and a backward Henyey-Greenstein lobe, weighted w and
+// 1 - w. A single lobe is w = 1.
+pub struct HenyeyGreensteinPdf {
+    pub uvw: ONB,
+    pub g1: f64,
+    pub g2: f64,
+    pub w: f64,
+}
+
+impl HenyeyGreensteinPdf {
+    pub fn new(direction: Vec3, g: f64) -> Self {
+        Self::new_double(direction, g, 0.0, 1.0)
+    }
+    pub fn new_double(direction: Vec3, g1: f64, g2: f64, w: f64) -> Self {
+        HenyeyGreensteinPdf {
+            uvw: ONB::build_from_w(direction),
+            g1,
+            g2,
+            w,
+        }
+    }
+    pub fn phase(&self, cos_theta: f64) -> f64 {
+        self.w * hg_phase(cos_theta, self.g1) + (1.0 - self.w) * hg_phase(cos_theta, self.g2)
+    }
+}
+
+impl Pdf for HenyeyGreensteinPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.phase(dot(unit_vector(direction), self.uvw.w()))
+    }
+    fn generate(&self) -> Vec3 {
+        let g = if random_double_default() < self.w {
+            self.g1
+        } else {
+            self.g2
+        };
+        let cos_theta = hg_sample_cos(g, random_double_default());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_double_default();
+        self.uvw
+            .local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::*;
+
+// below this solid angle the spherical construction loses precision and
+// uniform area sampling is just as good
+const MIN_SOLID_ANGLE: f64 = 1e-5;
+
+// Ureña et al. 2013, "An Area-Preserving Parametrization for Spherical
+// Rectangles". Samples the rectangle corner + s*ex + t*ey uniformly in the
+// solid angle it subtends from o. ex and ey must be perpendicular.
+pub struct SphericalRectangle {
+    o: Point3,
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+    z0: f64,
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    b0: f64,
+    b1: f64,
+    k: f64,
+    pub solid_angle: f64,
+}
+
+impl SphericalRectangle {
+    pub fn new(o: Point3, corner: Point3, ex: Vec3, ey: Vec3) -> Self {
+        let exl = ex.length();
+        let eyl = ey.length();
+        let x = ex / exl;
+        let y = ey / eyl;
+        let mut z = x ^ y;
+        let d = corner - o;
+        let mut z0 = dot(d, z);
+        if z0 > 0.0 {
+            z = -z;
+            z0 = -z0;
+        }
+        let x0 = dot(d, x);
+        let y0 = dot(d, y);
+        let x1 = x0 + exl;
+        let y1 = y0 + eyl;
+
+        let corner_at = |cx: f64, cy: f64| Vec3 {
+            x: cx,
+            y: cy,
+            z: z0,
+        };
+        let v00 = corner_at(x0, y0);
+        let v01 = corner_at(x0, y1);
+        let v10 = corner_at(x1, y0);
+        let v11 = corner_at(x1, y1);
+        let n0 = unit_vector(v00 ^ v10);
+        let n1 = unit_vector(v10 ^ v11);
+        let n2 = unit_vector(v11 ^ v01);
+        let n3 = unit_vector(v01 ^ v00);
+
+        let g0 = clamp(-dot(n0, n1), -1.0, 1.0).acos();
+        let g1 = clamp(-dot(n1, n2), -1.0, 1.0).acos();
+        let g2 = clamp(-dot(n2, n3), -1.0, 1.0).acos();
+        let g3 = clamp(-dot(n3, n0), -1.0, 1.0).acos();
+        let k = 2.0 * PI - g2 - g3;
+        let solid_angle = g0 + g1 - k;
+
+        SphericalRectangle {
+            o,
+            x,
+            y,
+            z,
+            z0,
+            x0,
+            y0,
+            x1,
+            y1,
+            b0: n0.z,
+            b1: n2.z,
+            k,
+            solid_angle: if solid_angle.is_nan() {
+                0.0
+            } else {
+                solid_angle
+            },
+        }
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.solid_angle > MIN_SOLID_ANGLE && self.z0 < -1e-8
+    }
+
+    pub fn sample(&self, u: f64, v: f64) -> Point3 {
+        let au = u * self.solid_angle + self.k;
+        let fu = (au.cos() * self.b0 - self.b1) / au.sin();
+        let sign = if fu > 0.0 { 1.0 } else { -1.0 };
+        let cu = clamp(sign / (fu * fu + self.b0 * self.b0).sqrt(), -1.0, 1.0);
+        let xu = -(cu * self.z0) / (1.0 - cu * cu).max(1e-12).sqrt();
+        let xu = clamp(xu, self.x0, self.x1);
+
+        let d = (xu * xu + self.z0 * self.z0).sqrt();
+        let h0 = self.y0 / (d * d + self.y0 * self.y0).sqrt();
+        let h1 = self.y1 / (d * d + self.y1 * self.y1).sqrt();
+        let hv = h0 + v * (h1 - h0);
+        let hv2 = hv * hv;
+        let yv = if hv2 < 1.0 - 1e-8 {
+            hv * d / (1.0 - hv2).sqrt()
+        } else {
+            self.y1
+        };
+        self.o + self.x * xu + self.y * yv + self.z * self.z0
+    }
+}
+
+fn use_solid_angle(sph: &SphericalRectangle, ex: Vec3, ey: Vec3) -> bool {
+    // skewed parallelograms fall back to area sampling
+    dot(ex, ey).abs() <= 1e-9 * ex.length() * ey.length() && sph.is_usable()
+}
+
+// Solid-angle density of rect_random for a direction v that hits the
+// rectangle at distance t * |v|.
+pub fn rect_pdf_value(o: Point3, corner: Point3, ex: Vec3, ey: Vec3, v: Vec3, t: f64) -> f64 {
+    let sph = SphericalRectangle::new(o, corner, ex, ey);
+    if use_solid_angle(&sph, ex, ey) {
+        1.0 / sph.solid_angle
+    } else {
+        let normal = ex ^ ey;
+        let area = normal.length();
+        let distance_squared = t * t * v.length_squared();
+        let cosine = (dot(v, normal) / (v.length() * area)).abs();
+        distance_squared / (cosine * area)
+    }
+}
+
+pub fn rect_random(o: Point3, corner: Point3, ex: Vec3, ey: Vec3) -> Vec3 {
+    let sph = SphericalRectangle::new(o, corner, ex, ey);
+    let u = random_double_default();
+    let v = random_double_default();
+    if use_solid_angle(&sph, ex, ey) {
+        sph.sample(u, v) - o
+    } else {
+        corner + ex * u + ey * v - o
+    }
+}
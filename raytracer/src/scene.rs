@@ -30,15 +30,15 @@ pub fn random_scene() -> HittableList {
             z: 0.9,
         },
     );
-    world.add(Arc::new(Sphere {
-        center: Point3 {
+    world.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: -1000.0,
             z: 0.0,
         },
-        radius: 1000.0,
-        mat_ptr: Lambertian { albedo: checker },
-    }));
+        1000.0,
+        Lambertian { albedo: checker },
+    )));
 
     for a in -11..11 {
         for b in -11..11 {
@@ -86,49 +86,41 @@ pub fn random_scene() -> HittableList {
                     let aldebo = random(0.5, 1.0);
                     let fuzz = random_double(0.0, 0.5);
                     let sphere_material = Metal::new(aldebo, fuzz);
-                    world.add(Arc::new(Sphere {
-                        center,
-                        radius: 0.2,
-                        mat_ptr: sphere_material,
-                    }));
+                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
                 } else {
                     //glass
                     let sphere_material = Dielectric::new(1.5);
-                    world.add(Arc::new(Sphere {
-                        center,
-                        radius: 0.2,
-                        mat_ptr: sphere_material,
-                    }));
+                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
                 }
             }
         }
     }
 
     let material1 = Dielectric::new(1.5);
-    world.add(Arc::new(Sphere {
-        center: Vec3 {
+    world.add(Arc::new(Sphere::new(
+        Vec3 {
             x: 0.0,
             y: 1.0,
             z: 0.0,
         },
-        radius: 1.0,
-        mat_ptr: material1,
-    }));
+        1.0,
+        material1,
+    )));
 
     let material2 = Lambertian::new(Vec3 {
         x: 0.4,
         y: 0.2,
         z: 0.1,
     });
-    world.add(Arc::new(Sphere {
-        center: Vec3 {
+    world.add(Arc::new(Sphere::new(
+        Vec3 {
             x: -4.0,
             y: 1.0,
             z: 0.0,
         },
-        radius: 1.0,
-        mat_ptr: material2,
-    }));
+        1.0,
+        material2,
+    )));
 
     let material3 = Metal::new(
         Vec3 {
@@ -138,15 +130,15 @@ pub fn random_scene() -> HittableList {
         },
         0.0,
     );
-    world.add(Arc::new(Sphere {
-        center: Vec3 {
+    world.add(Arc::new(Sphere::new(
+        Vec3 {
             x: 4.0,
             y: 1.0,
             z: 0.0,
         },
-        radius: 1.0,
-        mat_ptr: material3,
-    }));
+        1.0,
+        material3,
+    )));
 
     world
 }
@@ -165,26 +157,26 @@ pub fn two_spheres() -> HittableList {
             z: 0.9,
         },
     );
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: -10.0,
             z: 0.0,
         },
-        radius: 10.0,
-        mat_ptr: Lambertian {
+        10.0,
+        Lambertian {
             albedo: checker.clone(),
         },
-    }));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: 10.0,
             z: 0.0,
         },
-        radius: 10.0,
-        mat_ptr: Lambertian { albedo: checker },
-    }));
+        10.0,
+        Lambertian { albedo: checker },
+    )));
     objects
 }
 
@@ -193,26 +185,26 @@ pub fn two_perlin_spheres() -> HittableList {
 
     let pertext = NoiseTexture::new(4.0);
 
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: -1000.0,
             z: 0.0,
         },
-        radius: 1000.0,
-        mat_ptr: Lambertian {
+        1000.0,
+        Lambertian {
             albedo: pertext.clone(),
         },
-    }));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: 2.0,
             z: 0.0,
         },
-        radius: 2.0,
-        mat_ptr: Lambertian { albedo: pertext },
-    }));
+        2.0,
+        Lambertian { albedo: pertext },
+    )));
     objects
 }
 
@@ -221,15 +213,15 @@ pub fn earth() -> HittableList {
     //let earth_surface = Lambertian::new_texture(earth_texture);
 
     let mut objects = HittableList::new();
-    objects.add(Arc::new(Sphere {
-        center: Vec3 {
+    objects.add(Arc::new(Sphere::new(
+        Vec3 {
             x: 0.0,
             y: 0.0,
             z: 0.0,
         },
-        radius: 2.0,
-        mat_ptr: Lambertian::new_texture(ImageTexture::new("input/earthmap.jpg")),
-    }));
+        2.0,
+        Lambertian::new_texture(ImageTexture::new("input/earthmap.jpg")),
+    )));
     objects
 }
 
@@ -237,24 +229,24 @@ pub fn simple_light() -> HittableList {
     let mut objects: HittableList = HittableList::new();
 
     let pertext = NoiseTexture::new(4.0);
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: -1000.0,
             z: 0.0,
         },
-        radius: 1000.0,
-        mat_ptr: Lambertian::new_texture(pertext.clone()),
-    }));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+        1000.0,
+        Lambertian::new_texture(pertext.clone()),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: 2.0,
             z: 0.0,
         },
-        radius: 2.0,
-        mat_ptr: Lambertian::new_texture(pertext),
-    }));
+        2.0,
+        Lambertian::new_texture(pertext),
+    )));
 
     let difflight = DiffuseLight::new(Color {
         x: 4.0,
@@ -269,15 +261,15 @@ pub fn simple_light() -> HittableList {
         -2.0,
         difflight.clone(),
     )));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: 7.0,
             z: 0.0,
         },
-        radius: 2.0,
-        mat_ptr: difflight,
-    }));
+        2.0,
+        difflight,
+    )));
     objects
 }
 
@@ -308,32 +300,14 @@ pub fn arknights() -> HittableList {
         50.0,
     )));
 
-    objects.add(Arc::new(Sphere {
-        center: Vec3 {
+    objects.add(Arc::new(Sphere::new(
+        Vec3 {
             x: 0.0,
             y: -10000.0,
             z: 0.0,
         },
-        radius: 10000.0,
-        mat_ptr: aluminum,
-    }));
-
-    objects.add(Arc::new(ConstantMedium::new_color(
-        Sphere {
-            center: Point3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-            radius: 5000.0,
-            mat_ptr: Dielectric::new(1.5),
-        },
-        0.0001,
-        Color {
-            x: 1.0,
-            y: 1.0,
-            z: 1.0,
-        },
+        10000.0,
+        aluminum,
     )));
 
     let light = DiffuseLight::new(Color {
@@ -369,6 +343,51 @@ pub fn arknights() -> HittableList {
 
     objects
 }
+
+// arknights with cel shading, meant to be rendered with an Outline
+pub fn arknights_toon() -> HittableList {
+    let mut objects = HittableList::new();
+
+    objects.add(Arc::new(Sphere::new(
+        Vec3 {
+            x: 0.0,
+            y: -10000.0,
+            z: 0.0,
+        },
+        10000.0,
+        Toon::new_color(Color {
+            x: 0.85,
+            y: 0.85,
+            z: 0.8,
+        })
+        .with_bands(2, 0.6),
+    )));
+
+    let suzuran = load_obj_toon("obj/Suzuran/Suzuran.obj", 15.0);
+
+    objects.add(Arc::new(Translate::new(
+        RotateY::new(suzuran, 30.0),
+        Vec3 {
+            x: 420.0,
+            y: 0.0,
+            z: 1000.0,
+        },
+    )));
+
+    let shamare = load_obj_toon("obj/shamare/Shamare.obj", 15.0);
+
+    objects.add(Arc::new(Translate::new(
+        RotateY::new(shamare, -80.0),
+        Vec3 {
+            x: 70.0,
+            y: 0.0,
+            z: 1150.0,
+        },
+    )));
+
+    objects
+}
+
 pub fn final_scene() -> HittableList {
     let mut objects = HittableList::new();
     let mut boxes1 = HittableList::new();
@@ -440,23 +459,23 @@ pub fn final_scene() -> HittableList {
         mat_ptr: moving_sphere_material,
     }));
 
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 260.0,
             y: 150.0,
             z: 45.0,
         },
-        radius: 50.0,
-        mat_ptr: Dielectric::new(1.5),
-    }));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+        50.0,
+        Dielectric::new(1.5),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 0.0,
             y: 150.0,
             z: 145.0,
         },
-        radius: 50.0,
-        mat_ptr: Metal::new(
+        50.0,
+        Metal::new(
             Color {
                 x: 0.8,
                 y: 0.8,
@@ -464,20 +483,18 @@ pub fn final_scene() -> HittableList {
             },
             1.0,
         ),
-    }));
+    )));
 
-    let boundary = Sphere {
-        center: Point3 {
+    let boundary = Sphere::new(
+        Point3 {
             x: 360.0,
             y: 150.0,
             z: 145.0,
         },
-        radius: 70.0,
-        mat_ptr: Dielectric::new(1.5),
-    };
-    objects.add(Arc::new(boundary.clone()));
-
-    objects.add(Arc::new(ConstantMedium::new_color(
+        70.0,
+        Dielectric::new(1.5),
+    );
+    objects.add(Arc::new(InteriorMedium::new(
         boundary,
         0.2,
         Color {
@@ -487,15 +504,15 @@ pub fn final_scene() -> HittableList {
         },
     )));
     objects.add(Arc::new(ConstantMedium::new_color(
-        Sphere {
-            center: Point3 {
+        Sphere::new(
+            Point3 {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
             },
-            radius: 5000.0,
-            mat_ptr: Dielectric::new(1.5),
-        },
+            5000.0,
+            Dielectric::new(1.5),
+        ),
         0.0001,
         Color {
             x: 1.0,
@@ -505,25 +522,25 @@ pub fn final_scene() -> HittableList {
     )));
 
     let emat = Lambertian::new_texture(ImageTexture::new("input/earthmap.jpg"));
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 400.0,
             y: 200.0,
             z: 400.0,
         },
-        radius: 100.0,
-        mat_ptr: emat,
-    }));
+        100.0,
+        emat,
+    )));
     let pertext = NoiseTexture::new(0.1);
-    objects.add(Arc::new(Sphere {
-        center: Point3 {
+    objects.add(Arc::new(Sphere::new(
+        Point3 {
             x: 220.0,
             y: 280.0,
             z: 300.0,
         },
-        radius: 80.0,
-        mat_ptr: Lambertian::new_texture(pertext),
-    }));
+        80.0,
+        Lambertian::new_texture(pertext),
+    )));
 
     let mut boxes2 = HittableList::new();
     let white = Lambertian::new(Color {
@@ -533,11 +550,11 @@ pub fn final_scene() -> HittableList {
     });
     let ns = 1000;
     for _ in 0..ns {
-        boxes2.add(Arc::new(Sphere {
-            center: random(0.0, 165.0),
-            radius: 10.0,
-            mat_ptr: white.clone(),
-        }));
+        boxes2.add(Arc::new(Sphere::new(
+            random(0.0, 165.0),
+            10.0,
+            white.clone(),
+        )));
     }
     objects.add(Arc::new(Translate::new(
         RotateY::new(BvhNode::new_hittablelist(boxes2, 0.0, 1.0), 15.0),
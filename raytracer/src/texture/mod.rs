@@ -3,12 +3,28 @@
 use image::GenericImageView;
 
 mod perlin;
+pub mod voxel_grid;
 use perlin::*;
+pub use voxel_grid::*;
 
 use crate::*;
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+    // rough mean over the texture, used to estimate emitted power
+    fn average(&self) -> Color {
+        self.value(0.5, 0.5, Point3::new())
+    }
+}
+
+// lets shared textures be used wherever a texture type is expected
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.as_ref().value(u, v, p)
+    }
+    fn average(&self) -> Color {
+        self.as_ref().average()
+    }
 }
 
 #[derive(Clone)]
@@ -96,6 +112,31 @@ impl NoiseTexture {
     }
 }
 
+// Grey fractal turbulence, for the density of smoke and clouds
+#[derive(Clone)]
+pub struct TurbulenceTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub depth: i32,
+}
+
+impl TurbulenceTexture {
+    pub fn new(scale: f64, depth: i32) -> TurbulenceTexture {
+        TurbulenceTexture {
+            noise: Perlin::new(),
+            scale,
+            depth,
+        }
+    }
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let t = self.noise.turb(p * self.scale, self.depth);
+        Color { x: t, y: t, z: t }
+    }
+}
+
 #[derive(Clone)]
 pub struct ImageTexture {
     pub data: image::DynamicImage,
@@ -136,4 +177,68 @@ impl Texture for ImageTexture {
             z: pixel[2] as f64 * color_scale,
         }
     }
+    fn average(&self) -> Color {
+        let mut sum = Color::new();
+        for (_, _, pixel) in self.data.pixels() {
+            sum += Color {
+                x: pixel[0] as f64,
+                y: pixel[1] as f64,
+                z: pixel[2] as f64,
+            };
+        }
+        sum / (255.0 * (self.width * self.height).max(1) as f64)
+    }
+}
+
+// Emission colour of a black body at `temperature` kelvin. The colour has
+// unit luminance so `intensity` alone sets the brightness.
+#[derive(Clone)]
+pub struct BlackbodyTexture {
+    pub temperature: f64,
+    pub intensity: f64,
+    color: Color,
+}
+
+impl BlackbodyTexture {
+    pub fn new(temperature: f64, intensity: f64) -> BlackbodyTexture {
+        BlackbodyTexture {
+            temperature,
+            intensity,
+            color: blackbody(temperature) * intensity,
+        }
+    }
+}
+
+impl Texture for BlackbodyTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.color
+    }
+}
+
+// Blackbody emission whose temperature is read from the first channel of
+// another texture, e.g. a scalar map of kelvin values.
+#[derive(Clone)]
+pub struct TemperatureTexture<T: Texture> {
+    pub temperature: T,
+    pub intensity: f64,
+    table: Arc<BlackbodyTable>,
+}
+
+impl<T: Texture> TemperatureTexture<T> {
+    pub fn new(temperature: T, intensity: f64) -> TemperatureTexture<T> {
+        TemperatureTexture {
+            temperature,
+            intensity,
+            table: Arc::new(BlackbodyTable::new()),
+        }
+    }
+}
+
+impl<T: Texture> Texture for TemperatureTexture<T> {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.table.lookup(self.temperature.value(u, v, p).x) * self.intensity
+    }
+    fn average(&self) -> Color {
+        blackbody(self.temperature.average().x) * self.intensity
+    }
 }
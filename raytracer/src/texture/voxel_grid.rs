@@ -0,0 +1,190 @@
+use crate::*;
+
+// Scalar grid stretched over the box from `min` to `max`, read with
+// trilinear interpolation and zero outside the box. The raw file holds the
+// dimensions nx, ny and nz as little-endian u32 followed by nx * ny * nz
+// little-endian f32 values, x varying fastest.
+#[derive(Clone)]
+pub struct VoxelGridTexture {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub data: Vec<f64>,
+    pub min: Point3,
+    pub max: Point3,
+    pub max_value: f64,
+}
+
+impl VoxelGridTexture {
+    pub fn new(filename: &str, min: Point3, max: Point3) -> Self {
+        let bytes = std::fs::read(filename).expect("Cannot open.");
+        assert!(
+            bytes.len() >= 12,
+            "{} is too short for a voxel grid header.",
+            filename
+        );
+        let word = |i: usize| {
+            [
+                bytes[4 * i],
+                bytes[4 * i + 1],
+                bytes[4 * i + 2],
+                bytes[4 * i + 3],
+            ]
+        };
+        let nx = u32::from_le_bytes(word(0)) as usize;
+        let ny = u32::from_le_bytes(word(1)) as usize;
+        let nz = u32::from_le_bytes(word(2)) as usize;
+        let size = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .and_then(|n| n.checked_add(3))
+            .and_then(|n| n.checked_mul(4));
+        assert!(
+            matches!(size, Some(size) if bytes.len() >= size),
+            "{} holds {} bytes, too few for a {}x{}x{} voxel grid.",
+            filename,
+            bytes.len(),
+            nx,
+            ny,
+            nz
+        );
+        let data = (0..nx * ny * nz)
+            .map(|i| f32::from_le_bytes(word(3 + i)) as f64)
+            .collect();
+        Self::from_data(nx, ny, nz, data, min, max)
+    }
+
+    pub fn from_data(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        data: Vec<f64>,
+        min: Point3,
+        max: Point3,
+    ) -> Self {
+        assert!(nx > 0 && ny > 0 && nz > 0 && data.len() == nx * ny * nz);
+        let max_value = data.iter().cloned().fold(0.0, f64::max);
+        VoxelGridTexture {
+            nx,
+            ny,
+            nz,
+            data,
+            min,
+            max,
+            max_value,
+        }
+    }
+
+    fn at(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[(z * self.ny + y) * self.nx + x]
+    }
+
+    pub fn lookup(&self, p: Point3) -> f64 {
+        let size = self.max - self.min;
+        let local = p - self.min;
+        let (fx, fy, fz) = (local.x / size.x, local.y / size.y, local.z / size.z);
+        if !(0.0..=1.0).contains(&fx) || !(0.0..=1.0).contains(&fy) || !(0.0..=1.0).contains(&fz) {
+            return 0.0;
+        }
+        // values sit at cell centres
+        let cell = |f: f64, n: usize| {
+            let x = clamp(f * n as f64 - 0.5, 0.0, (n - 1) as f64);
+            let i = (x.floor() as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f64)
+        };
+        let (x0, x1, tx) = cell(fx, self.nx);
+        let (y0, y1, ty) = cell(fy, self.ny);
+        let (z0, z1, tz) = cell(fz, self.nz);
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.at(x0, y0, z), self.at(x1, y0, z), tx),
+                lerp(self.at(x0, y1, z), self.at(x1, y1, z), tx),
+                ty,
+            )
+        };
+        lerp(plane(z0), plane(z1), tz)
+    }
+}
+
+impl Texture for VoxelGridTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let d = self.lookup(p);
+        Color { x: d, y: d, z: d }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_raw_grid_and_interpolates() {
+        let (nx, ny, nz) = (3, 2, 2);
+        let mut bytes = Vec::new();
+        for n in [nx, ny, nz] {
+            bytes.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        for i in 0..nx * ny * nz {
+            bytes.extend_from_slice(&(i as f32).to_le_bytes());
+        }
+        let path = std::env::temp_dir().join("raytracer_voxel_grid_test.raw");
+        std::fs::write(&path, &bytes).unwrap();
+        // one unit per cell, so cell centres sit at half-integers
+        let grid = VoxelGridTexture::new(
+            path.to_str().unwrap(),
+            Point3::new(),
+            Point3 {
+                x: 3.0,
+                y: 2.0,
+                z: 2.0,
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((grid.nx, grid.ny, grid.nz), (3, 2, 2));
+        assert_eq!(grid.max_value, 11.0);
+        let at = |x: f64, y: f64, z: f64| grid.lookup(Point3 { x, y, z });
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let expected = ((z * ny + y) * nx + x) as f64;
+                    let value = at(x as f64 + 0.5, y as f64 + 0.5, z as f64 + 0.5);
+                    assert!((value - expected).abs() < 1e-9);
+                }
+            }
+        }
+        // halfway between centres along each axis, clamped past the outer ones
+        assert!((at(1.0, 0.5, 0.5) - 0.5).abs() < 1e-9);
+        assert!((at(0.5, 1.0, 1.0) - 4.5).abs() < 1e-9);
+        assert!((at(0.1, 0.1, 0.1) - 0.0).abs() < 1e-9);
+        assert_eq!(at(3.5, 1.0, 1.0), 0.0);
+    }
+
+    fn load_bytes(name: &str, bytes: &[u8]) -> VoxelGridTexture {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        let max = Point3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        VoxelGridTexture::new(path.to_str().unwrap(), Point3::new(), max)
+    }
+
+    #[test]
+    #[should_panic(expected = "too short for a voxel grid header")]
+    fn rejects_truncated_header() {
+        load_bytes("raytracer_voxel_grid_header.raw", &[2, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "too few for a 4294967295x4294967295x2 voxel grid")]
+    fn rejects_overflowing_dimensions() {
+        let mut bytes = Vec::new();
+        for n in [u32::MAX, u32::MAX, 2] {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        load_bytes("raytracer_voxel_grid_overflow.raw", &bytes);
+    }
+}
@@ -22,14 +22,33 @@ pub fn write_color(pixel_color: Vec3, samples_per_pixel: u32) -> [u8; 3] {
     ]
 }
 
+pub fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Color {
+    Color {
+        x: 3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        y: -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        z: 0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    }
+}
+
 pub struct ColorInformation {
     pub i: u32,
     pub j: u32,
     pub color: Color,
+    // the same sum split by light group
+    pub groups: Vec<Color>,
 }
 
 impl ColorInformation {
-    pub fn new(i: u32, j: u32, color: Color) -> ColorInformation {
-        ColorInformation { i, j, color }
+    pub fn new(i: u32, j: u32, groups: Vec<Color>) -> ColorInformation {
+        ColorInformation {
+            i,
+            j,
+            color: sum_groups(&groups),
+            groups,
+        }
     }
 }
@@ -9,13 +9,17 @@ pub use std::{fs::File, process::exit};
 pub mod canny;
 pub mod color;
 pub mod obj_loader;
+pub mod outline;
 pub mod ray;
+pub mod spectrum;
 pub mod vec3;
 
 pub use canny::*;
 pub use color::*;
 pub use obj_loader::*;
+pub use outline::*;
 pub use ray::*;
+pub use spectrum::*;
 pub use vec3::*;
 
 pub const PI: f64 = std::f64::consts::PI;
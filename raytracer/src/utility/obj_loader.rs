@@ -211,7 +211,100 @@ pub fn load_obj_without_mat(path: &str, scale: f64) -> HittableList {
     objects
 }
 
+fn mtl_color(values: &str) -> Option<Color> {
+    let v: Vec<f64> = values
+        .split_whitespace()
+        .filter_map(|x| x.parse().ok())
+        .collect();
+    match v.len() {
+        0 => None,
+        1 | 2 => Some(Color {
+            x: v[0],
+            y: v[0],
+            z: v[0],
+        }),
+        _ => Some(Color {
+            x: v[0],
+            y: v[1],
+            z: v[2],
+        }),
+    }
+}
+
+fn base_color_from_mtl(m: &tobj::Material) -> Arc<dyn Texture> {
+    match (&m.diffuse_texture, m.diffuse) {
+        (Some(file), _) => Arc::new(ImageTexture::new(file)),
+        (None, Some(kd)) => Arc::new(SolidColor::new(Color {
+            x: kd[0] as f64,
+            y: kd[1] as f64,
+            z: kd[2] as f64,
+        })),
+        (None, None) => constant_texture(0.8),
+    }
+}
+
+// Maps a Wavefront material, including the PBR extension (Pr, Pm, Ps, Pc,
+// Ke and their map_ textures), onto the principled material.
+pub fn principled_from_mtl(m: &tobj::Material) -> Principled {
+    let param = |key: &str| -> Option<Arc<dyn Texture>> {
+        if let Some(file) = m.unknown_param.get(&format!("map_{}", key)) {
+            return Some(Arc::new(ImageTexture::new(file)));
+        }
+        m.unknown_param
+            .get(key)
+            .and_then(|values| mtl_color(values))
+            .map(|c| Arc::new(SolidColor::new(c)) as Arc<dyn Texture>)
+    };
+
+    let mut mat = Principled::new(base_color_from_mtl(m));
+    if let Some(roughness) = param("Pr") {
+        mat.roughness = roughness;
+    } else if let Some(ns) = m.shininess {
+        // Blinn-Phong exponent to GGX roughness
+        mat.roughness = constant_texture((2.0 / (ns as f64 + 2.0)).sqrt().sqrt());
+    }
+    if let Some(metallic) = param("Pm") {
+        mat.metallic = metallic;
+    }
+    if let Some(sheen) = param("Ps") {
+        mat.sheen = sheen;
+    }
+    if let Some(clearcoat) = param("Pc") {
+        mat.clearcoat = clearcoat;
+    }
+    if let Some(emission) = param("Ke") {
+        mat.emission = emission;
+    }
+    if let Some(d) = m.dissolve {
+        mat.transmission = constant_texture(1.0 - d as f64);
+    }
+    if let Some(ni) = m.optical_density {
+        if ni > 0.0 {
+            mat.ir = ni as f64;
+        }
+    }
+    mat
+}
+
+// Cel-shaded counterpart of principled_from_mtl, keeping only the base colour.
+pub fn toon_from_mtl(m: &tobj::Material) -> Toon<Arc<dyn Texture>> {
+    Toon::new(base_color_from_mtl(m))
+}
+
 pub fn load_obj(path: &str, scale: f64) -> HittableList {
+    load_obj_with(path, scale, principled_from_mtl)
+}
+
+pub fn load_obj_toon(path: &str, scale: f64) -> HittableList {
+    load_obj_with(path, scale, toon_from_mtl)
+}
+
+// loads every model with the material `to_material` makes of its MTL entry
+pub fn load_obj_with<M: Material + 'static>(
+    path: &str,
+    scale: f64,
+    to_material: impl Fn(&tobj::Material) -> M,
+) -> HittableList {
     let cornell_box = tobj::load_obj(
         path,
         &LoadOptions {
@@ -235,9 +328,8 @@ pub fn load_obj(path: &str, scale: f64) -> HittableList {
         let material_id = mesh.material_id.unwrap();
         let mut boxes = HittableList::new();
 
-        let mat_ = Lambertian::new_texture(ImageTexture::new(
-            materials[material_id].diffuse_texture.as_ref().unwrap(),
-        ));
+        let mut mat_ = to_material(&materials[material_id]);
+        let mut area = 0.0;
 
         let mat = Lambertian::new(Color {
             x: 0.2,
@@ -314,9 +406,12 @@ pub fn load_obj(path: &str, scale: f64) -> HittableList {
 
             // cur.set_normal(na, nb, nc);
 
+            area += cur.area;
             boxes.add(Arc::new(cur));
         }
 
+        // the whole mesh is one emitter
+        mat_.resolve_area(area);
         //objects.add(Arc::new(BvhNode::new_hittablelist(boxes, 0.0, 1.0)));
         objects.add(Arc::new(BvhNode::new_hittablelist_with_mat(
             boxes, 0.0, 1.0, mat_,
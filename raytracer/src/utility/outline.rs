@@ -0,0 +1,120 @@
+use crate::*;
+
+// What the camera sees through a pixel centre. Meshes carry one material
+// each, so the material's address stands in for an object ID.
+#[derive(Clone, Copy)]
+pub struct GeometrySample {
+    pub depth: f64,
+    pub normal: Vec3,
+    pub id: usize,
+}
+
+pub fn geometry_buffer(
+    world: &HittableList,
+    cam: &Camera,
+    width: u32,
+    height: u32,
+) -> Vec<Option<GeometrySample>> {
+    let mut buffer = Vec::with_capacity((width * height) as usize);
+    // rows top to bottom, as in the image
+    for j in (0..height).rev() {
+        for i in 0..width {
+            let u = (i as f64 + 0.5) / ((width - 1) as f64);
+            let v = (j as f64 + 0.5) / ((height - 1) as f64);
+            let r = cam.get_ray(u, v, 0.0, 1.0);
+            buffer.push(world.hit(&r, 0.001, INFINITY).map(|rec| GeometrySample {
+                depth: rec.t * r.dir.length(),
+                normal: rec.normal,
+                id: rec.mat_ptr as *const dyn Material as *const u8 as usize,
+            }));
+        }
+    }
+    buffer
+}
+
+// Lines drawn where the geometry seen by neighbouring pixels is
+// discontinuous: a different object, a jump in depth relative to the
+// nearer side, or a crease sharper than `normal_threshold` (a cosine).
+// Only the nearer pixel of a pair is marked, which keeps lines one pixel
+// wide and on the silhouette of the front object.
+#[derive(Clone, Copy)]
+pub struct Outline {
+    pub color: Color,
+    pub depth_threshold: f64,
+    pub normal_threshold: f64,
+}
+
+impl Outline {
+    pub fn new(color: Color) -> Self {
+        Outline {
+            color,
+            depth_threshold: 0.05,
+            normal_threshold: 0.5,
+        }
+    }
+
+    fn is_edge(&self, a: &Option<GeometrySample>, b: &Option<GeometrySample>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                a.depth <= b.depth
+                    && (a.id != b.id
+                        || b.depth - a.depth > self.depth_threshold * a.depth
+                        || dot(a.normal, b.normal) < self.normal_threshold)
+            }
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn detect(&self, buffer: &[Option<GeometrySample>], width: u32, height: u32) -> Vec<bool> {
+        let (width, height) = (width as usize, height as usize);
+        let mut edges = vec![false; width * height];
+        for j in 0..height {
+            for i in 0..width {
+                let index = j * width + i;
+                let mut neighbours = Vec::with_capacity(4);
+                if i > 0 {
+                    neighbours.push(index - 1);
+                }
+                if i + 1 < width {
+                    neighbours.push(index + 1);
+                }
+                if j > 0 {
+                    neighbours.push(index - width);
+                }
+                if j + 1 < height {
+                    neighbours.push(index + width);
+                }
+                edges[index] = neighbours
+                    .iter()
+                    .any(|&n| self.is_edge(&buffer[index], &buffer[n]));
+            }
+        }
+        edges
+    }
+
+    // Paints the outlines of what cam sees in world over img and the light
+    // group buffers, where the lines go to the default group.
+    pub fn draw(
+        &self,
+        img: &mut RgbImage,
+        groups: &mut [Vec<Color>],
+        world: &HittableList,
+        cam: &Camera,
+    ) {
+        let (width, height) = img.dimensions();
+        let buffer = geometry_buffer(world, cam, width, height);
+        let color = image::Rgb(write_color(self.color, 1));
+        for (index, _) in self
+            .detect(&buffer, width, height)
+            .iter()
+            .enumerate()
+            .filter(|(_, &edge)| edge)
+        {
+            img.put_pixel(index as u32 % width, index as u32 / width, color);
+            for (group, pixels) in groups.iter_mut().enumerate() {
+                pixels[index] = if group == 0 { self.color } else { Color::new() };
+            }
+        }
+    }
+}
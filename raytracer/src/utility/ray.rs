@@ -1,10 +1,149 @@
 use crate::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// deepest nesting of dielectrics a path keeps track of
+pub const MAX_NESTED_MEDIA: usize = 4;
+
+// 0 marks an empty stack slot
+static NEXT_MEDIUM_ID: AtomicU32 = AtomicU32::new(1);
+
+// A dielectric a path can be inside of. When volumes overlap, the one with
+// the highest priority is the medium actually present. Media are told apart
+// by id, so copies of one (the triangles of a mesh, say) are the same medium
+// while each NestedMedium::new is a different one.
+#[derive(Clone, Copy)]
+pub struct NestedMedium {
+    pub id: u32,
+    pub priority: u32,
+    pub ir: f64,
+    pub dispersion: Dispersion,
+    pub absorption: Color,
+}
+
+impl NestedMedium {
+    pub fn new(priority: u32, ir: f64, dispersion: Dispersion, absorption: Color) -> Self {
+        NestedMedium {
+            id: NEXT_MEDIUM_ID.fetch_add(1, Ordering::Relaxed),
+            priority,
+            ir,
+            dispersion,
+            absorption,
+        }
+    }
+    // same as Dielectric::ior
+    pub fn ior(&self, lambda: f64) -> f64 {
+        if lambda > 0.0 {
+            self.dispersion.ior(lambda * 1e-3).unwrap_or(self.ir)
+        } else {
+            self.ir
+        }
+    }
+    // what a path at wavelength `lambda` keeps of the medium once inside
+    pub fn entry(&self, lambda: f64) -> MediumEntry {
+        MediumEntry {
+            id: self.id,
+            priority: self.priority,
+            ior: self.ior(lambda) as f32,
+            absorption: [
+                self.absorption.x as f32,
+                self.absorption.y as f32,
+                self.absorption.z as f32,
+            ],
+        }
+    }
+}
+
+// A medium on a path's stack, with its index already taken at the path's
+// wavelength. Single precision keeps rays small.
+#[derive(Clone, Copy)]
+pub struct MediumEntry {
+    pub id: u32,
+    pub priority: u32,
+    pub ior: f32,
+    pub absorption: [f32; 3],
+}
+
+impl MediumEntry {
+    const EMPTY: MediumEntry = MediumEntry {
+        id: 0,
+        priority: 0,
+        ior: 1.0,
+        absorption: [0.0; 3],
+    };
+    // Beer-Lambert falloff over `distance`, which may be infinite
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let channel = |sigma: f32| {
+            if sigma > 0.0 {
+                (-sigma as f64 * distance).exp()
+            } else {
+                1.0
+            }
+        };
+        Color {
+            x: channel(self.absorption[0]),
+            y: channel(self.absorption[1]),
+            z: channel(self.absorption[2]),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MediumStack {
+    entries: [MediumEntry; MAX_NESTED_MEDIA],
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        MediumStack {
+            entries: [MediumEntry::EMPTY; MAX_NESTED_MEDIA],
+        }
+    }
+    fn iter(&self) -> impl Iterator<Item = &MediumEntry> {
+        self.entries.iter().filter(|e| e.id != 0)
+    }
+    // a full stack forgets its oldest entry
+    pub fn push(&mut self, entry: MediumEntry) {
+        let last = MAX_NESTED_MEDIA - 1;
+        if self.entries[last].id != 0 {
+            self.entries.rotate_left(1);
+            self.entries[last] = MediumEntry::EMPTY;
+        }
+        if let Some(slot) = self.entries.iter_mut().find(|e| e.id == 0) {
+            *slot = entry;
+        }
+    }
+    // removes the most recent entry of the medium, keeping the order
+    pub fn remove(&mut self, id: u32) {
+        if let Some(i) = self.entries.iter().rposition(|e| e.id == id && id != 0) {
+            self.entries[i..].rotate_left(1);
+            self.entries[MAX_NESTED_MEDIA - 1] = MediumEntry::EMPTY;
+        }
+    }
+    pub fn contains(&self, id: u32) -> bool {
+        self.iter().any(|e| e.id == id)
+    }
+    // the medium that is present, the latest entered among equal priorities
+    pub fn top(&self) -> Option<MediumEntry> {
+        let mut top: Option<MediumEntry> = None;
+        for &e in self.iter() {
+            match top {
+                Some(t) if e.priority < t.priority => {}
+                _ => top = Some(e),
+            }
+        }
+        top
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
     pub tm: f64,
+    // wavelength in nm carried by the path, 0 when rendering in RGB
+    pub lambda: f64,
+    // nested dielectrics the path is currently inside
+    pub media: MediumStack,
 }
 
 impl Ray {
@@ -19,6 +158,8 @@ impl Ray {
             orig: origin,
             dir: direction,
             tm: 0.0,
+            lambda: 0.0,
+            media: MediumStack::new(),
         }
     }
 
@@ -27,6 +168,18 @@ impl Ray {
             orig: origin,
             dir: direction,
             tm: time,
+            lambda: 0.0,
+            media: MediumStack::new(),
+        }
+    }
+
+    // continues the path from a new origin, keeping its time, wavelength
+    // and media
+    pub fn scattered(&self, origin: Point3, direction: Vec3) -> Ray {
+        Ray {
+            orig: origin,
+            dir: direction,
+            ..*self
         }
     }
 
@@ -35,3 +188,80 @@ impl Ray {
         self.orig + self.dir * t
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn medium(priority: u32) -> NestedMedium {
+        NestedMedium::new(priority, 1.5, Dispersion::Constant, Color::new())
+    }
+
+    fn top_id(stack: &MediumStack) -> u32 {
+        stack.top().unwrap().id
+    }
+
+    #[test]
+    fn top_is_highest_priority_then_latest() {
+        let (water, glass, ice) = (medium(1), medium(2), medium(2));
+        let mut stack = MediumStack::new();
+        assert!(stack.top().is_none());
+        stack.push(glass.entry(0.0));
+        stack.push(water.entry(0.0));
+        assert_eq!(top_id(&stack), glass.id);
+        stack.push(ice.entry(0.0));
+        assert_eq!(top_id(&stack), ice.id);
+        stack.remove(ice.id);
+        assert_eq!(top_id(&stack), glass.id);
+        stack.remove(glass.id);
+        assert_eq!(top_id(&stack), water.id);
+    }
+
+    #[test]
+    fn remove_takes_the_latest_entry_only() {
+        let (a, b) = (medium(1), medium(1));
+        let mut stack = MediumStack::new();
+        stack.push(a.entry(0.0));
+        stack.push(b.entry(0.0));
+        stack.push(a.entry(0.0));
+        stack.remove(a.id);
+        assert!(stack.contains(a.id) && stack.contains(b.id));
+        assert_eq!(top_id(&stack), b.id);
+        // removing what is not on the stack changes nothing
+        stack.remove(medium(3).id);
+        stack.remove(a.id);
+        assert!(!stack.contains(a.id));
+        assert_eq!(top_id(&stack), b.id);
+    }
+
+    #[test]
+    fn full_stack_forgets_oldest() {
+        let media: Vec<_> = (0..=MAX_NESTED_MEDIA).map(|_| medium(1)).collect();
+        let mut stack = MediumStack::new();
+        for m in &media {
+            stack.push(m.entry(0.0));
+        }
+        assert!(!stack.contains(media[0].id));
+        assert!(media[1..].iter().all(|m| stack.contains(m.id)));
+        assert_eq!(top_id(&stack), media[MAX_NESTED_MEDIA].id);
+    }
+
+    #[test]
+    fn copies_share_a_medium_and_new_ones_do_not() {
+        let a = medium(1);
+        let copy = a;
+        let mut stack = MediumStack::new();
+        stack.push(a.entry(0.0));
+        assert!(stack.contains(copy.id));
+        assert!(!stack.contains(medium(1).id));
+    }
+
+    #[test]
+    fn transmittance_handles_clear_channels_at_infinity() {
+        let mut m = medium(1);
+        m.absorption.x = 0.5;
+        let t = m.entry(0.0).transmittance(INFINITY);
+        assert_eq!((t.x, t.y, t.z), (0.0, 1.0, 1.0));
+        assert!((m.entry(0.0).transmittance(2.0).x - (-1.0f64).exp()).abs() < 1e-7);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::*;
+
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+fn piecewise_gaussian(x: f64, mu: f64, sigma1: f64, sigma2: f64) -> f64 {
+    let t = (x - mu) / if x < mu { sigma1 } else { sigma2 };
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 2-degree colour matching functions, multi-lobe fit from
+// Wyman, Sloan and Shirley 2013. lambda in nanometres.
+pub fn cie_x(lambda: f64) -> f64 {
+    1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2)
+}
+
+pub fn cie_y(lambda: f64) -> f64 {
+    0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1)
+}
+
+pub fn cie_z(lambda: f64) -> f64 {
+    1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8)
+}
+
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    Vec3 {
+        x: cie_x(lambda),
+        y: cie_y(lambda),
+        z: cie_z(lambda),
+    }
+}
+
+// Spectral radiance of a black body in W / (sr m^2 nm).
+pub fn planck(lambda: f64, kelvin: f64) -> f64 {
+    const H: f64 = 6.62607015e-34;
+    const C: f64 = 299792458.0;
+    const K: f64 = 1.380649e-23;
+    let l = lambda * 1e-9;
+    2.0 * H * C * C / (l.powi(5) * ((H * C / (l * K * kelvin)).exp() - 1.0)) * 1e-9
+}
+
+// Integrates a spectrum against the matching functions.
+pub fn spectrum_to_xyz(spectrum: impl Fn(f64) -> f64) -> Vec3 {
+    let step = 5.0;
+    let mut xyz = Vec3::new();
+    let mut lambda = LAMBDA_MIN;
+    while lambda <= LAMBDA_MAX {
+        xyz += cie_xyz(lambda) * spectrum(lambda) * step;
+        lambda += step;
+    }
+    xyz
+}
+
+// Linear sRGB colour of a black body, normalised to unit luminance.
+pub fn blackbody(kelvin: f64) -> Color {
+    if kelvin <= 0.0 {
+        return Color::new();
+    }
+    let xyz = spectrum_to_xyz(|lambda| planck(lambda, kelvin));
+    let rgb = xyz_to_linear_srgb(xyz / xyz.y);
+    Color {
+        x: rgb.x.max(0.0),
+        y: rgb.y.max(0.0),
+        z: rgb.z.max(0.0),
+    }
+}
+
+// blackbody colours tabulated every BLACKBODY_STEP kelvin over this range
+const BLACKBODY_MIN: f64 = 100.0;
+const BLACKBODY_MAX: f64 = 40000.0;
+const BLACKBODY_STEP: f64 = 10.0;
+
+// blackbody() precomputed for lookups per hit, interpolated between entries
+// and clamped to the ends of the range
+pub struct BlackbodyTable {
+    colors: Vec<Color>,
+}
+
+impl BlackbodyTable {
+    pub fn new() -> Self {
+        let count = ((BLACKBODY_MAX - BLACKBODY_MIN) / BLACKBODY_STEP) as usize + 1;
+        BlackbodyTable {
+            colors: (0..count)
+                .map(|i| blackbody(BLACKBODY_MIN + i as f64 * BLACKBODY_STEP))
+                .collect(),
+        }
+    }
+
+    pub fn lookup(&self, kelvin: f64) -> Color {
+        if kelvin <= 0.0 {
+            return Color::new();
+        }
+        let x = (clamp(kelvin, BLACKBODY_MIN, BLACKBODY_MAX) - BLACKBODY_MIN) / BLACKBODY_STEP;
+        let i = (x as usize).min(self.colors.len() - 2);
+        let t = x - i as f64;
+        self.colors[i] * (1.0 - t) + self.colors[i + 1] * t
+    }
+}
+
+// Smits 1999, "An RGB to Spectrum Conversion for Reflectances": ten bins
+// from 380 to 720 nm for white and the six primaries and secondaries.
+const SMITS_MIN: f64 = 380.0;
+const SMITS_MAX: f64 = 720.0;
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// Value at lambda of the spectrum Smits' method builds for an RGB colour.
+// Only scaling the colour scales the result; the spectrum of a product of
+// colours is not the product of their spectra, so each factor has to be
+// upsampled on its own and the values multiplied afterwards.
+pub fn rgb_to_spectrum(c: Color, lambda: f64) -> f64 {
+    let bin = ((lambda - SMITS_MIN) / (SMITS_MAX - SMITS_MIN) * 10.0).floor();
+    let i = clamp(bin, 0.0, 9.0) as usize;
+    let (r, g, b) = (c.x, c.y, c.z);
+    if r <= g && r <= b {
+        r * SMITS_WHITE[i]
+            + if g <= b {
+                (g - r) * SMITS_CYAN[i] + (b - g) * SMITS_BLUE[i]
+            } else {
+                (b - r) * SMITS_CYAN[i] + (g - b) * SMITS_GREEN[i]
+            }
+    } else if g <= r && g <= b {
+        g * SMITS_WHITE[i]
+            + if r <= b {
+                (r - g) * SMITS_MAGENTA[i] + (b - r) * SMITS_BLUE[i]
+            } else {
+                (b - g) * SMITS_MAGENTA[i] + (r - b) * SMITS_RED[i]
+            }
+    } else {
+        b * SMITS_WHITE[i]
+            + if r <= g {
+                (r - b) * SMITS_YELLOW[i] + (g - r) * SMITS_GREEN[i]
+            } else {
+                (g - b) * SMITS_YELLOW[i] + (r - g) * SMITS_RED[i]
+            }
+    }
+}
+
+// Picks a wavelength per path and turns the radiance it carried back into
+// RGB. The result is scaled per channel so a flat unit spectrum comes out
+// as white.
+#[derive(Clone, Copy)]
+pub struct SpectralSampler {
+    white: Color,
+}
+
+impl SpectralSampler {
+    pub fn new() -> Self {
+        SpectralSampler {
+            white: xyz_to_linear_srgb(spectrum_to_xyz(|_| 1.0)),
+        }
+    }
+
+    pub fn sample(&self, u: f64) -> (f64, f64) {
+        (
+            LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN),
+            1.0 / (LAMBDA_MAX - LAMBDA_MIN),
+        )
+    }
+
+    pub fn radiance_to_rgb(&self, lambda: f64, radiance: f64, pdf: f64) -> Color {
+        let rgb = xyz_to_linear_srgb(cie_xyz(lambda) * (radiance / pdf));
+        Color {
+            x: rgb.x / self.white.x,
+            y: rgb.y / self.white.y,
+            z: rgb.z / self.white.z,
+        }
+    }
+}
+
+// In spectral mode (lambda > 0) a colour is replaced by the value of its
+// upsampled spectrum in every channel; in RGB mode it is left alone.
+pub fn color_at_wavelength(c: Color, lambda: f64) -> Color {
+    if lambda > 0.0 {
+        let s = rgb_to_spectrum(c, lambda);
+        Color { x: s, y: s, z: s }
+    } else {
+        c
+    }
+}